@@ -1,23 +1,21 @@
-use bela::{Bela, BelaApplication, DigitalDirection, Error, RenderContext};
+use bela::{Bela, BelaApplication, Error, PwmOutput, RenderContext};
 
-struct DigitalExample(usize);
+struct DigitalExample {
+    led: PwmOutput,
+}
 
 unsafe impl BelaApplication for DigitalExample {
     fn render(&mut self, context: &mut RenderContext) {
-        context.pin_mode(0, 0, DigitalDirection::Output);
-        let ten_ms_in_frames = (context.digital_sample_rate() / 100.) as usize;
-        let hundred_ms_in_frames = (context.digital_sample_rate() / 10.) as usize;
-        for f in 0..context.digital_frames() {
-            let v = self.0 < ten_ms_in_frames;
-            context.digital_write_once(f, 0, v);
-            self.0 += 1;
-            if self.0 > hundred_ms_in_frames {
-                self.0 = 0;
-            }
-        }
+        self.led.process(context);
     }
 }
 
 fn main() -> Result<(), Error> {
-    Bela::new(|_| Some(DigitalExample(0))).run()
+    Bela::new(|context| {
+        // blink for 10 ms every 100 ms
+        Some(DigitalExample {
+            led: PwmOutput::new(context.digital_output(0).ok()?, 10., 0.1),
+        })
+    })
+    .run()
 }
@@ -0,0 +1,23 @@
+use bela::{Bela, BelaApplication, Error, OfflineRender, RenderContext};
+use std::time::Duration;
+
+struct OfflineExample(usize);
+
+unsafe impl BelaApplication for OfflineExample {
+    fn render(&mut self, context: &mut RenderContext) {
+        let sample_rate = context.audio_sample_rate();
+        for mut frame in context.audio_out_view().frames_mut() {
+            let signal = 2. * (self.0 as f32 * 110. / sample_rate) - 1.;
+            self.0 += 1;
+            if self.0 as f32 > sample_rate / 110. {
+                self.0 = 0;
+            }
+            frame.fill(0.5 * signal);
+        }
+    }
+}
+
+fn main() -> Result<(), Error> {
+    Bela::new(|_| Some(OfflineExample(0)))
+        .render_offline(OfflineRender::new(Duration::from_secs(2), "offline.wav"))
+}
@@ -0,0 +1,768 @@
+use std::panic::UnwindSafe;
+use std::str::FromStr;
+
+use crate::{Bela, BelaApplication, BelaHw, SetupContext};
+
+/// Internal description of a command-line option
+struct BelaOption {
+    long: &'static str,
+    short: Option<char>,
+    value: Option<&'static str>,
+    help: &'static str,
+}
+
+/// Standard options accepted by Bela programs, mirroring `Bela_getopt_long`
+///
+/// PRU options are not supported, as selecting PRU code is unsafe.
+const BELA_OPTIONS: &[BelaOption] = &[
+    BelaOption {
+        long: "period",
+        short: Some('p'),
+        value: Some("frames"),
+        help: "Set the number of analog frames per period",
+    },
+    BelaOption {
+        long: "verbose",
+        short: Some('v'),
+        value: None,
+        help: "Enable verbose logging",
+    },
+    BelaOption {
+        long: "use-analog",
+        short: Some('N'),
+        value: Some("0|1"),
+        help: "Enable or disable the analog inputs and outputs",
+    },
+    BelaOption {
+        long: "use-digital",
+        short: Some('G'),
+        value: Some("0|1"),
+        help: "Enable or disable the digital inputs and outputs",
+    },
+    BelaOption {
+        long: "analog-channels",
+        short: Some('C'),
+        value: Some("channels"),
+        help: "Set the number of analog input and output channels",
+    },
+    BelaOption {
+        long: "digital-channels",
+        short: Some('B'),
+        value: Some("channels"),
+        help: "Set the number of digital channels",
+    },
+    BelaOption {
+        long: "mute-speaker",
+        short: Some('M'),
+        value: Some("0|1"),
+        help: "Begin with the speakers muted",
+    },
+    BelaOption {
+        long: "dac-level",
+        short: Some('D'),
+        value: Some("dB"),
+        help: "Set the audio DAC level",
+    },
+    BelaOption {
+        long: "adc-level",
+        short: Some('A'),
+        value: Some("dB"),
+        help: "Set the audio ADC level",
+    },
+    BelaOption {
+        long: "pga-gain-left",
+        short: None,
+        value: Some("dB"),
+        help: "Set the gain of the left PGA channel",
+    },
+    BelaOption {
+        long: "pga-gain-right",
+        short: None,
+        value: Some("dB"),
+        help: "Set the gain of the right PGA channel",
+    },
+    BelaOption {
+        long: "hp-level",
+        short: Some('H'),
+        value: Some("dB"),
+        help: "Set the headphone level",
+    },
+    BelaOption {
+        long: "mux",
+        short: Some('X'),
+        value: Some("channels"),
+        help: "Set the number of multiplexer channels (0 to disable)",
+    },
+    BelaOption {
+        long: "audio-expander-inputs",
+        short: Some('Y'),
+        value: Some("channels"),
+        help: "Set the audio expander inputs (comma-separated analog channels)",
+    },
+    BelaOption {
+        long: "audio-expander-outputs",
+        short: Some('Z'),
+        value: Some("channels"),
+        help: "Set the audio expander outputs (comma-separated analog channels)",
+    },
+    BelaOption {
+        long: "detect-underruns",
+        short: None,
+        value: Some("0|1"),
+        help: "Enable or disable underrun detection and logging",
+    },
+    BelaOption {
+        long: "disable-led",
+        short: None,
+        value: None,
+        help: "Disable the blinking LED indicating Bela is running",
+    },
+    BelaOption {
+        long: "stop-button-pin",
+        short: None,
+        value: Some("pin"),
+        help: "Set the stop button pin (-1 to disable)",
+    },
+    BelaOption {
+        long: "amp-mute-pin",
+        short: None,
+        value: Some("pin"),
+        help: "Set the amplifier mute pin (-1 to disable)",
+    },
+    BelaOption {
+        long: "high-performance-mode",
+        short: None,
+        value: None,
+        help: "Enable high performance mode, may affect IDE / Linux performance",
+    },
+    BelaOption {
+        long: "interleave",
+        short: None,
+        value: Some("0|1"),
+        help: "Enable or disable interleaving of samples",
+    },
+    BelaOption {
+        long: "analog-outputs-persist",
+        short: None,
+        value: Some("0|1"),
+        help: "Set if analog outputs should persist",
+    },
+    BelaOption {
+        long: "uniform-sample-rate",
+        short: None,
+        value: Some("0|1"),
+        help: "Set if analog inputs should be resampled to audio rate",
+    },
+    BelaOption {
+        long: "audio-thread-stack-size",
+        short: None,
+        value: Some("bytes"),
+        help: "Set the audio thread stack size",
+    },
+    BelaOption {
+        long: "auxiliary-task-stack-size",
+        short: None,
+        value: Some("bytes"),
+        help: "Set the stack size of auxiliary task threads",
+    },
+    BelaOption {
+        long: "board",
+        short: None,
+        value: Some("board"),
+        help: "Select the board instead of detecting it (e.g., Bela, BelaMini)",
+    },
+    BelaOption {
+        long: "help",
+        short: Some('h'),
+        value: None,
+        help: "Print this help and exit",
+    },
+];
+
+/// A user-defined command-line option, parsed by `Bela::from_args_with`
+/// in addition to the standard Bela options
+#[derive(Debug, Clone, Copy)]
+pub struct CustomOption {
+    long: &'static str,
+    short: Option<char>,
+    value: Option<&'static str>,
+    help: &'static str,
+}
+
+impl CustomOption {
+    /// Create an option `--long` without a value
+    pub const fn flag(long: &'static str, help: &'static str) -> Self {
+        Self {
+            long,
+            short: None,
+            value: None,
+            help,
+        }
+    }
+
+    /// Create an option `--long <value_name>` taking a value
+    pub const fn value(long: &'static str, value_name: &'static str, help: &'static str) -> Self {
+        Self {
+            long,
+            short: None,
+            value: Some(value_name),
+            help,
+        }
+    }
+
+    /// Add a short alias `-short`
+    ///
+    /// Like the long name, it must not clash with the standard Bela
+    /// options, otherwise `Bela::from_args_with` returns
+    /// `ArgsError::ConflictingOption`.
+    pub const fn short(mut self, short: char) -> Self {
+        self.short = Some(short);
+        self
+    }
+}
+
+/// User-defined options and positional arguments found by
+/// `Bela::from_args_with`
+#[derive(Debug, Clone, Default)]
+pub struct CustomArgs {
+    options: Vec<(&'static str, Option<String>)>,
+    positional: Vec<String>,
+}
+
+impl CustomArgs {
+    /// Check if the option `--long` was given
+    pub fn is_present(&self, long: &str) -> bool {
+        self.options.iter().any(|(option, _)| *option == long)
+    }
+
+    /// The value of the last occurrence of option `--long`
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| *option == long)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// The values of all occurrences of option `--long`
+    pub fn values<'a>(&'a self, long: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.options
+            .iter()
+            .filter(move |(option, _)| *option == long)
+            .filter_map(|(_, value)| value.as_deref())
+    }
+
+    /// All arguments that are not options
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+}
+
+/// Error returned when parsing command-line arguments
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgsError {
+    /// `--help` was given, contains the usage text
+    Help(String),
+    /// An unknown option was given
+    UnknownOption(String),
+    /// An option requiring a value was given without one
+    MissingValue(String),
+    /// An option was given with an invalid value
+    InvalidValue { option: String, value: String },
+    /// A user-defined option clashes with a standard Bela option
+    ConflictingOption(String),
+}
+
+impl ArgsError {
+    /// Print the usage text to stdout and exit successfully for
+    /// `ArgsError::Help`, or print the error to stderr and exit with a
+    /// failure code otherwise
+    pub fn exit(&self) -> ! {
+        match self {
+            ArgsError::Help(usage) => {
+                print!("{}", usage);
+                std::process::exit(0)
+            }
+            _ => {
+                eprintln!("{}", self);
+                std::process::exit(1)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            ArgsError::Help(usage) => write!(f, "{}", usage),
+            ArgsError::UnknownOption(option) => write!(f, "unknown option {}", option),
+            ArgsError::MissingValue(option) => write!(f, "missing value for option {}", option),
+            ArgsError::InvalidValue { option, value } => {
+                write!(f, "invalid value {:?} for option {}", value, option)
+            }
+            ArgsError::ConflictingOption(option) => {
+                write!(f, "option --{} clashes with a Bela option", option)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+/// Internal helper to format a single line of the usage text
+fn usage_line(
+    usage: &mut String,
+    long: &str,
+    short: Option<char>,
+    value: Option<&str>,
+    help: &str,
+) {
+    let mut option = match short {
+        Some(short) => format!("  -{}, --{}", short, long),
+        None => format!("      --{}", long),
+    };
+    if let Some(value) = value {
+        option.push_str(&format!(" <{}>", value));
+    }
+    usage.push_str(&format!("{:<42} {}\n", option, help));
+}
+
+/// Internal helper to create the usage text
+fn usage(program: &str, custom: &[CustomOption]) -> String {
+    let mut usage = format!("Usage: {} [options]\n\nBela options:\n", program);
+    for option in BELA_OPTIONS {
+        usage_line(
+            &mut usage,
+            option.long,
+            option.short,
+            option.value,
+            option.help,
+        );
+    }
+    if !custom.is_empty() {
+        usage.push_str("\nApplication options:\n");
+        for option in custom {
+            usage_line(
+                &mut usage,
+                option.long,
+                option.short,
+                option.value,
+                option.help,
+            );
+        }
+    }
+    usage
+}
+
+/// Internal description of an option found in the arguments
+#[derive(Clone, Copy)]
+struct FoundOption {
+    long: &'static str,
+    value: Option<&'static str>,
+    custom: bool,
+}
+
+/// Internal helper to look up an option by its long or short name,
+/// preferring the standard Bela options
+fn find_option(
+    custom: &[CustomOption],
+    long: Option<&str>,
+    short: Option<char>,
+) -> Option<FoundOption> {
+    let matches = |option_long: &str, option_short: Option<char>| {
+        Some(option_long) == long || (short.is_some() && option_short == short)
+    };
+    let bela = BELA_OPTIONS
+        .iter()
+        .find(|option| matches(option.long, option.short))
+        .map(|option| FoundOption {
+            long: option.long,
+            value: option.value,
+            custom: false,
+        });
+    bela.or_else(|| {
+        custom
+            .iter()
+            .find(|option| matches(option.long, option.short))
+            .map(|option| FoundOption {
+                long: option.long,
+                value: option.value,
+                custom: true,
+            })
+    })
+}
+
+/// Internal helper to parse a board name, ignoring case
+fn parse_board(name: &str) -> Option<BelaHw> {
+    let boards = [
+        ("NoHw", BelaHw::NoHw),
+        ("Bela", BelaHw::Bela),
+        ("BelaMini", BelaHw::BelaMini),
+        ("Salt", BelaHw::Salt),
+        ("CtagFace", BelaHw::CtagFace),
+        ("CtagBeast", BelaHw::CtagBeast),
+        ("CtagFaceBela", BelaHw::CtagFaceBela),
+        ("CtagBeastBela", BelaHw::CtagBeastBela),
+    ];
+    boards
+        .iter()
+        .find(|(board, _)| board.eq_ignore_ascii_case(name))
+        .map(|&(_, board)| board)
+}
+
+/// Internal helper to parse a `0|1` option value
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "0" | "false" => Some(false),
+        "1" | "true" => Some(true),
+        _ => None,
+    }
+}
+
+/// Internal helper to parse a pin number, with -1 disabling the pin
+fn parse_pin(value: &str) -> Option<Option<i8>> {
+    match i8::from_str(value).ok()? {
+        -1 => Some(None),
+        pin => Some(Some(pin)),
+    }
+}
+
+/// Internal helper to parse the channels of the audio expander as a bit
+/// mask, given as a comma-separated list of analog channels like in the
+/// Bela core, or directly as a hexadecimal mask, e.g., `0x3`
+fn parse_channel_mask(value: &str) -> Option<usize> {
+    if let Some(mask) = value.strip_prefix("0x") {
+        return usize::from_str_radix(mask, 16).ok();
+    }
+    value.split(',').try_fold(0, |mask, channel| {
+        let channel = u32::from_str(channel.trim()).ok()?;
+        Some(mask | 1usize.checked_shl(channel)?)
+    })
+}
+
+impl<Application, Constructor> Bela<Constructor>
+where
+    Application: BelaApplication,
+    Constructor: Send + UnwindSafe + FnOnce(&mut SetupContext) -> Option<Application>,
+{
+    /// Create a new `Bela` builder from command-line arguments
+    ///
+    /// Parses the standard Bela options (`--period`, `--use-analog`,
+    /// `--dac-level`, `--board`, `--high-performance-mode`, `--mux`, ...)
+    /// just like C++ Bela projects. The first argument is the program
+    /// name. On `--help`, `ArgsError::Help` is returned containing the
+    /// usage text, which can be printed using `ArgsError::exit`:
+    ///
+    /// ```no_run
+    /// # use bela::{Bela, BelaApplication, RenderContext};
+    /// # struct Example;
+    /// # unsafe impl BelaApplication for Example {
+    /// #     fn render(&mut self, _context: &mut RenderContext) {}
+    /// # }
+    /// let bela = Bela::from_args(|_context| Some(Example), std::env::args())
+    ///     .unwrap_or_else(|err| err.exit());
+    /// ```
+    pub fn from_args<Args>(constructor: Constructor, args: Args) -> Result<Self, ArgsError>
+    where
+        Args: IntoIterator,
+        Args::Item: Into<String>,
+    {
+        Self::from_args_with(constructor, args, &[]).map(|(bela, _)| bela)
+    }
+
+    /// Create a new `Bela` builder from command-line arguments, accepting
+    /// user-defined options in addition to the standard Bela options
+    ///
+    /// See `Bela::from_args`. Returns the builder and the user-defined
+    /// options and positional arguments found. Short options can be
+    /// combined, e.g., `-vp 32`, and the value of the last one may be
+    /// attached, e.g., `-vp32`. Returns `ArgsError::ConflictingOption` if
+    /// a user-defined option uses the name of a standard option.
+    pub fn from_args_with<Args>(
+        constructor: Constructor,
+        args: Args,
+        custom: &[CustomOption],
+    ) -> Result<(Self, CustomArgs), ArgsError>
+    where
+        Args: IntoIterator,
+        Args::Item: Into<String>,
+    {
+        if let Some(option) = custom
+            .iter()
+            .find(|option| find_option(&[], Some(option.long), option.short).is_some())
+        {
+            return Err(ArgsError::ConflictingOption(option.long.into()));
+        }
+
+        let mut args = args.into_iter().map(Into::into);
+        let program = args.next().unwrap_or_else(|| "bela".into());
+
+        let mut bela = Self::new(constructor);
+        let mut custom_args = CustomArgs::default();
+        let mut only_positional = false;
+
+        while let Some(arg) = args.next() {
+            if only_positional || arg == "-" || !arg.starts_with('-') {
+                custom_args.positional.push(arg);
+                continue;
+            }
+            if arg == "--" {
+                only_positional = true;
+                continue;
+            }
+
+            // options given by the argument, with their attached values
+            let mut options = Vec::new();
+            if let Some(option) = arg.strip_prefix("--") {
+                let (long, attached) = match option.find('=') {
+                    Some(index) => (&option[..index], Some(option[index + 1..].to_string())),
+                    None => (option, None),
+                };
+                let option = find_option(custom, Some(long), None)
+                    .ok_or_else(|| ArgsError::UnknownOption(arg.clone()))?;
+                options.push((option, attached));
+            } else {
+                // combined short options end at the first one taking a value
+                for (index, short) in arg.char_indices().skip(1) {
+                    let option = find_option(custom, None, Some(short))
+                        .ok_or_else(|| ArgsError::UnknownOption(format!("-{}", short)))?;
+                    if option.value.is_some() {
+                        let rest = &arg[index + short.len_utf8()..];
+                        options.push((
+                            option,
+                            Some(rest.to_string()).filter(|rest| !rest.is_empty()),
+                        ));
+                        break;
+                    }
+                    options.push((option, None));
+                }
+            }
+
+            for (option, attached) in options {
+                let value = match (option.value, attached) {
+                    (Some(_), Some(value)) => Some(value),
+                    (Some(_), None) => match args.next() {
+                        Some(value) => Some(value),
+                        None => return Err(ArgsError::MissingValue(arg)),
+                    },
+                    (None, Some(value)) => {
+                        return Err(ArgsError::InvalidValue { option: arg, value })
+                    }
+                    (None, None) => None,
+                };
+                if option.custom {
+                    custom_args.options.push((option.long, value));
+                } else if option.long == "help" {
+                    return Err(ArgsError::Help(usage(&program, custom)));
+                } else {
+                    let value = value.as_deref().unwrap_or_default();
+                    bela = bela.apply_option(option.long, value, &arg)?;
+                }
+            }
+        }
+
+        Ok((bela, custom_args))
+    }
+
+    /// Internal helper applying the standard option `--name`, given as
+    /// `arg`
+    fn apply_option(self, name: &str, value: &str, arg: &str) -> Result<Self, ArgsError> {
+        let invalid = |value: &str| ArgsError::InvalidValue {
+            option: arg.into(),
+            value: value.into(),
+        };
+        let parse_usize = |value: &str| usize::from_str(value).map_err(|_| invalid(value));
+        let parse_f32 = |value: &str| f32::from_str(value).map_err(|_| invalid(value));
+        let parse_bool = |value: &str| parse_bool(value).ok_or_else(|| invalid(value));
+        let parse_pin = |value: &str| parse_pin(value).ok_or_else(|| invalid(value));
+        let parse_channel_mask =
+            |value: &str| parse_channel_mask(value).ok_or_else(|| invalid(value));
+
+        Ok(match name {
+            "period" => self.period_size(parse_usize(value)?),
+            "verbose" => self.verbose(true),
+            "use-analog" => self.use_analog(parse_bool(value)?),
+            "use-digital" => self.use_digital(parse_bool(value)?),
+            "analog-channels" => {
+                let channels = parse_usize(value)?;
+                self.num_analog_in_channels(channels)
+                    .num_analog_out_channels(channels)
+            }
+            "digital-channels" => self.num_digital_channels(parse_usize(value)?),
+            "mute-speaker" => self.begin_muted(parse_bool(value)?),
+            "dac-level" => self.dac_level(parse_f32(value)?),
+            "adc-level" => self.adc_level(parse_f32(value)?),
+            "pga-gain-left" => {
+                let right = self.settings.pgaGain[1];
+                self.pga_gain([parse_f32(value)?, right])
+            }
+            "pga-gain-right" => {
+                let left = self.settings.pgaGain[0];
+                self.pga_gain([left, parse_f32(value)?])
+            }
+            "hp-level" => self.headphone_level(parse_f32(value)?),
+            "mux" => self.num_mux_channels(parse_usize(value)?),
+            "audio-expander-inputs" => self.audio_expander_inputs(parse_channel_mask(value)?),
+            "audio-expander-outputs" => self.audio_expander_outputs(parse_channel_mask(value)?),
+            "detect-underruns" => self.detect_underruns(parse_bool(value)?),
+            "disable-led" => self.enable_led(false),
+            "stop-button-pin" => self.stop_button_pin(parse_pin(value)?),
+            "amp-mute-pin" => self.amp_mute_pin(parse_pin(value)?),
+            "high-performance-mode" => self.high_performance_mode(true),
+            "interleave" => self.interleave(parse_bool(value)?),
+            "analog-outputs-persist" => self.analog_outputs_persist(parse_bool(value)?),
+            "uniform-sample-rate" => self.uniform_sample_rate(parse_bool(value)?),
+            "audio-thread-stack-size" => self.audio_thread_stack_size(parse_usize(value)?),
+            "auxiliary-task-stack-size" => self.auxiliary_task_stack_size(parse_usize(value)?),
+            "board" => self.board(parse_board(value).ok_or_else(|| invalid(value))?),
+            _ => return Err(ArgsError::UnknownOption(arg.into())),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RenderContext;
+
+    struct Nothing;
+
+    unsafe impl BelaApplication for Nothing {
+        fn render(&mut self, _context: &mut RenderContext) {}
+    }
+
+    type Constructor = fn(&mut SetupContext) -> Option<Nothing>;
+
+    const CUSTOM: &[CustomOption] = &[
+        CustomOption::flag("loop", "Loop the sample").short('l'),
+        CustomOption::value("file", "path", "Sample to play").short('f'),
+    ];
+
+    fn parse(args: &[&str]) -> Result<(Bela<Constructor>, CustomArgs), ArgsError> {
+        let args = std::iter::once("bela").chain(args.iter().copied());
+        Bela::from_args_with(|_| Some(Nothing), args, CUSTOM)
+    }
+
+    #[test]
+    fn parses_standard_options() {
+        let (bela, custom) =
+            parse(&["--period", "32", "-N", "0", "--dac-level=-3", "-A-6"]).unwrap();
+        assert_eq!(bela.settings.periodSize, 32);
+        assert_eq!(bela.settings.useAnalog, 0);
+        assert_eq!(bela.settings.dacLevel, -3.);
+        assert_eq!(bela.settings.adcLevel, -6.);
+        assert!(custom.positional().is_empty());
+
+        let (bela, _) = parse(&["--board", "belamini", "--stop-button-pin", "-1"]).unwrap();
+        assert_eq!(bela.settings.board, BelaHw::BelaMini as _);
+        assert_eq!(bela.settings.stopButtonPin, -1);
+    }
+
+    #[test]
+    fn parses_audio_expander_channels() {
+        let (bela, _) = parse(&["--audio-expander-inputs", "0,1,4", "-Z", "3"]).unwrap();
+        assert_eq!(bela.settings.audioExpanderInputs, 0b10011);
+        assert_eq!(bela.settings.audioExpanderOutputs, 0b1000);
+        let (bela, _) = parse(&["--audio-expander-inputs=0x3", "-Z0xc0"]).unwrap();
+        assert_eq!(bela.settings.audioExpanderInputs, 0x3);
+        assert_eq!(bela.settings.audioExpanderOutputs, 0xc0);
+        for value in &["", "0,", "1;2", "0xg"] {
+            assert!(parse(&["-Y", value]).is_err(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn parses_combined_short_options() {
+        let (bela, custom) = parse(&["-vN", "0", "-lp16", "-vlf", "a.wav"]).unwrap();
+        assert_eq!(bela.settings.verbose, 1);
+        assert_eq!(bela.settings.useAnalog, 0);
+        assert_eq!(bela.settings.periodSize, 16);
+        assert_eq!(custom.values("loop").count(), 0);
+        assert!(custom.is_present("loop"));
+        assert_eq!(custom.value("file"), Some("a.wav"));
+    }
+
+    #[test]
+    fn parses_custom_options_and_positional_arguments() {
+        let args = [
+            "in.wav",
+            "--file=a.wav",
+            "-f",
+            "b.wav",
+            "-",
+            "--",
+            "--period",
+        ];
+        let (bela, custom) = parse(&args).unwrap();
+        assert_eq!(bela.settings.periodSize, 16);
+        assert!(!custom.is_present("loop"));
+        assert_eq!(custom.value("file"), Some("b.wav"));
+        assert_eq!(
+            custom.values("file").collect::<Vec<_>>(),
+            ["a.wav", "b.wav"]
+        );
+        assert_eq!(custom.positional(), ["in.wav", "-", "--period"]);
+    }
+
+    #[test]
+    fn reports_errors() {
+        let error = |args: &[&str]| parse(args).err().unwrap();
+        assert_eq!(error(&["--foo"]), ArgsError::UnknownOption("--foo".into()));
+        assert_eq!(error(&["-vx"]), ArgsError::UnknownOption("-x".into()));
+        assert_eq!(error(&["-vp"]), ArgsError::MissingValue("-vp".into()));
+        assert_eq!(
+            error(&["--period", "many"]),
+            ArgsError::InvalidValue {
+                option: "--period".into(),
+                value: "many".into()
+            }
+        );
+        assert_eq!(
+            error(&["--verbose=1"]),
+            ArgsError::InvalidValue {
+                option: "--verbose=1".into(),
+                value: "1".into()
+            }
+        );
+        assert_eq!(
+            error(&["--board", "Beagle"]),
+            ArgsError::InvalidValue {
+                option: "--board".into(),
+                value: "Beagle".into()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_options() {
+        let parse = |custom: &[CustomOption]| {
+            let constructor: Constructor = |_| Some(Nothing);
+            Bela::from_args_with(constructor, ["bela"], custom).err()
+        };
+        assert_eq!(
+            parse(&[CustomOption::value("period", "ms", "Period")]),
+            Some(ArgsError::ConflictingOption("period".into()))
+        );
+        assert_eq!(
+            parse(&[CustomOption::flag("pan", "Pan").short('p')]),
+            Some(ArgsError::ConflictingOption("pan".into()))
+        );
+        assert_eq!(parse(CUSTOM), None);
+    }
+
+    #[test]
+    fn prints_help() {
+        let usage = match parse(&["-vh"]) {
+            Err(ArgsError::Help(usage)) => usage,
+            _ => panic!("expected help"),
+        };
+        assert!(usage.starts_with("Usage: bela [options]\n\nBela options:\n"));
+        assert!(usage.contains("  -p, --period <frames>"));
+        assert!(usage.contains("      --board <board>"));
+        let (bela_options, application_options) = usage.split_once("Application options:").unwrap();
+        assert!(bela_options.contains("  -h, --help"));
+        assert!(application_options.contains("  -l, --loop "));
+        assert!(application_options.contains("  -f, --file <path>"));
+        assert!(application_options.ends_with("Sample to play\n"));
+    }
+}
@@ -1,6 +1,7 @@
 use std::ffi::c_void;
 use std::panic::catch_unwind;
 
+use crate::platform::{Current, Platform};
 use crate::{Context, Error, SetupContext};
 
 /// Handle to a created auxiliary Bela task
@@ -13,6 +14,18 @@ pub struct AuxiliaryTask(bela_sys::AuxiliaryTask);
 
 unsafe impl Send for AuxiliaryTask {}
 
+impl AuxiliaryTask {
+    /// Internal helper scheduling the task without a context
+    pub(crate) fn schedule(&self) -> Result<(), Error> {
+        let res = unsafe { Current::schedule_auxiliary_task(self.0) };
+
+        match res {
+            0 => Ok(()),
+            _ => Err(Error::ScheduleTask),
+        }
+    }
+}
+
 impl SetupContext {
     /// Create an auxiliary task that runs on a lower-priority thread
     ///
@@ -47,10 +60,10 @@ impl SetupContext {
         // let's be explicit about which part is actually unsafe here
         #[allow(unused_unsafe)]
         let aux_task = unsafe {
-            bela_sys::Bela_createAuxiliaryTask(
-                Some(auxiliary_task_trampoline::<Auxiliary>),
+            Current::create_auxiliary_task(
+                auxiliary_task_trampoline::<Auxiliary>,
                 priority,
-                name.as_ptr(),
+                name,
                 task_ptr,
             )
         };
@@ -66,11 +79,6 @@ impl SetupContext {
 impl<T> Context<T> {
     /// Schedule a created auxiliary task
     pub fn schedule_auxiliary_task(&mut self, task: &AuxiliaryTask) -> Result<(), Error> {
-        let res = unsafe { bela_sys::Bela_scheduleAuxiliaryTask(task.0) };
-
-        match res {
-            0 => Ok(()),
-            _ => Err(Error::ScheduleTask),
-        }
+        task.schedule()
     }
 }
@@ -1,4 +1,5 @@
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub enum BelaHw {
     NoHw = bela_sys::BelaHw_BelaHw_NoHw as isize,
@@ -10,3 +11,341 @@ pub enum BelaHw {
     CtagFaceBela = bela_sys::BelaHw_BelaHw_CtagFaceBela as isize,
     CtagBeastBela = bela_sys::BelaHw_BelaHw_CtagBeastBela as isize,
 }
+
+impl BelaHw {
+    /// Internal conversion from the raw `bela_sys::BelaHw` value
+    pub(crate) fn from_raw(raw: bela_sys::BelaHw) -> Option<Self> {
+        [
+            BelaHw::NoHw,
+            BelaHw::Bela,
+            BelaHw::BelaMini,
+            BelaHw::Salt,
+            BelaHw::CtagFace,
+            BelaHw::CtagBeast,
+            BelaHw::CtagFaceBela,
+            BelaHw::CtagBeastBela,
+        ]
+        .iter()
+        .copied()
+        .find(|&hw| hw as bela_sys::BelaHw == raw)
+    }
+
+    /// Capabilities of the board
+    ///
+    /// ```
+    /// use bela::BelaHw;
+    ///
+    /// let capabilities = BelaHw::BelaMini.capabilities();
+    /// assert_eq!(capabilities.analog_out_channels, 0);
+    /// assert_eq!(capabilities.digital_pin(1).unwrap().header, "P2.02");
+    /// ```
+    pub fn capabilities(self) -> &'static BoardCapabilities {
+        match self {
+            BelaHw::NoHw => &NO_HW,
+            BelaHw::Bela => &BELA,
+            BelaHw::BelaMini => &BELA_MINI,
+            BelaHw::Salt => &SALT,
+            BelaHw::CtagFace => &CTAG_FACE,
+            BelaHw::CtagBeast => &CTAG_BEAST,
+            BelaHw::CtagFaceBela => &CTAG_FACE_BELA,
+            BelaHw::CtagBeastBela => &CTAG_BEAST_BELA,
+        }
+    }
+}
+
+/// A digital channel and the header pin it is connected to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DigitalPin {
+    /// Channel index as used by the digital functions of `Context`
+    pub channel: usize,
+    /// Header pin label, e.g., `"P8_07"` on Bela or `"P2.02"` on BelaMini
+    pub header: &'static str,
+    /// Linux GPIO number
+    pub gpio: u8,
+}
+
+/// Static description of what a board provides
+///
+/// Available for every board via `BelaHw::capabilities`, without
+/// requiring the hardware to be present.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct BoardCapabilities {
+    /// Audio sample rate in Hz
+    pub audio_sample_rate: f32,
+    /// Number of audio input channels
+    pub audio_in_channels: usize,
+    /// Number of audio output channels
+    pub audio_out_channels: usize,
+    /// Maximum number of analog input channels
+    pub analog_in_channels: usize,
+    /// Maximum number of analog output channels
+    pub analog_out_channels: usize,
+    /// Digital channels, ordered by channel index
+    pub digital_pins: &'static [DigitalPin],
+    /// GPIO of the stop button, if the board has one
+    pub stop_button_pin: Option<u8>,
+    /// GPIO muting the speaker amplifiers, if the board has amplifiers
+    pub amp_mute_pin: Option<u8>,
+    /// GPIO of the status LED blinking while running, see
+    /// `Bela::enable_led`
+    pub led_pin: Option<u8>,
+    /// Whether the analog multiplexer capelet is supported
+    pub supports_mux: bool,
+    /// Whether the audio expander capelet is supported
+    pub supports_audio_expander: bool,
+}
+
+impl BoardCapabilities {
+    /// Number of digital channels
+    pub fn digital_channels(&self) -> usize {
+        self.digital_pins.len()
+    }
+
+    /// Look up the header pin of a digital channel
+    pub fn digital_pin(&self, channel: usize) -> Option<&'static DigitalPin> {
+        self.digital_pins.get(channel)
+    }
+
+    /// Look up a digital channel by its header pin label
+    pub fn digital_pin_by_header(&self, header: &str) -> Option<&'static DigitalPin> {
+        self.digital_pins.iter().find(|pin| pin.header == header)
+    }
+
+    /// Check if `gpio` is available on the board, either as a digital
+    /// channel or as stop button, amplifier mute or status LED pin
+    pub fn has_gpio(&self, gpio: u8) -> bool {
+        self.digital_pins.iter().any(|pin| pin.gpio == gpio)
+            || [self.stop_button_pin, self.amp_mute_pin, self.led_pin].contains(&Some(gpio))
+    }
+}
+
+/// Internal helper to create a `DigitalPin` table entry
+const fn pin(channel: usize, header: &'static str, gpio: u8) -> DigitalPin {
+    DigitalPin {
+        channel,
+        header,
+        gpio,
+    }
+}
+
+/// Digital channels of the Bela cape, which are GPIOs on the BeagleBone
+/// Black headers, also used by CTAG capes without a Bela cape
+const BELA_DIGITAL: &[DigitalPin] = &[
+    pin(0, "P8_07", 66),
+    pin(1, "P8_08", 67),
+    pin(2, "P8_09", 69),
+    pin(3, "P8_10", 68),
+    pin(4, "P8_11", 45),
+    pin(5, "P8_12", 44),
+    pin(6, "P9_12", 60),
+    pin(7, "P9_14", 50),
+    pin(8, "P8_15", 47),
+    pin(9, "P8_16", 46),
+    pin(10, "P9_16", 51),
+    pin(11, "P8_18", 65),
+    pin(12, "P8_27", 86),
+    pin(13, "P8_28", 88),
+    pin(14, "P8_29", 87),
+    pin(15, "P8_30", 89),
+];
+
+/// Digital channels of the BelaMini cape
+const BELA_MINI_DIGITAL: &[DigitalPin] = &[
+    pin(0, "P2.01", 50),
+    pin(1, "P2.02", 59),
+    pin(2, "P2.04", 58),
+    pin(3, "P2.06", 57),
+    pin(4, "P2.08", 60),
+    pin(5, "P2.10", 52),
+    pin(6, "P2.18", 47),
+    pin(7, "P2.20", 64),
+    pin(8, "P2.22", 46),
+    pin(9, "P2.24", 44),
+    pin(10, "P2.33", 45),
+    pin(11, "P2.35", 86),
+    pin(12, "P1.35", 88),
+    pin(13, "P1.02", 87),
+    pin(14, "P1.04", 89),
+    pin(15, "P1.06", 5),
+];
+
+/// GPIO of the stop button on Bela and BelaMini
+const STOP_BUTTON_PIN: u8 = 115;
+/// GPIO muting the speaker amplifiers on Bela
+const AMP_MUTE_PIN: u8 = 61;
+/// GPIO of the user LED USR3 of the BeagleBone, which all boards use as
+/// status LED
+const LED_PIN: u8 = 56;
+
+const NO_HW: BoardCapabilities = BoardCapabilities {
+    audio_sample_rate: 0.,
+    audio_in_channels: 0,
+    audio_out_channels: 0,
+    analog_in_channels: 0,
+    analog_out_channels: 0,
+    digital_pins: &[],
+    stop_button_pin: None,
+    amp_mute_pin: None,
+    led_pin: None,
+    supports_mux: false,
+    supports_audio_expander: false,
+};
+
+const BELA: BoardCapabilities = BoardCapabilities {
+    audio_sample_rate: 44100.,
+    audio_in_channels: 2,
+    audio_out_channels: 2,
+    analog_in_channels: 8,
+    analog_out_channels: 8,
+    digital_pins: BELA_DIGITAL,
+    stop_button_pin: Some(STOP_BUTTON_PIN),
+    amp_mute_pin: Some(AMP_MUTE_PIN),
+    led_pin: Some(LED_PIN),
+    supports_mux: true,
+    supports_audio_expander: true,
+};
+
+const BELA_MINI: BoardCapabilities = BoardCapabilities {
+    analog_out_channels: 0,
+    digital_pins: BELA_MINI_DIGITAL,
+    amp_mute_pin: None,
+    supports_mux: false,
+    ..BELA
+};
+
+/// Salt is built around a Bela cape, its panel exposes the channels of the
+/// cape
+const SALT: BoardCapabilities = BoardCapabilities {
+    audio_sample_rate: 44100.,
+    audio_in_channels: 2,
+    audio_out_channels: 2,
+    analog_in_channels: 8,
+    analog_out_channels: 8,
+    digital_pins: BELA_DIGITAL,
+    stop_button_pin: Some(STOP_BUTTON_PIN),
+    amp_mute_pin: Some(AMP_MUTE_PIN),
+    led_pin: Some(LED_PIN),
+    supports_mux: true,
+    supports_audio_expander: true,
+};
+
+/// CTAG capes run on a BeagleBone Black without a Bela cape, so there are
+/// no analog channels, stop button or amplifiers, and the codec runs at
+/// 48 kHz
+const CTAG_FACE: BoardCapabilities = BoardCapabilities {
+    audio_sample_rate: 48000.,
+    audio_in_channels: 4,
+    audio_out_channels: 8,
+    analog_in_channels: 0,
+    analog_out_channels: 0,
+    digital_pins: BELA_DIGITAL,
+    stop_button_pin: None,
+    amp_mute_pin: None,
+    led_pin: Some(LED_PIN),
+    supports_mux: false,
+    supports_audio_expander: false,
+};
+
+const CTAG_BEAST: BoardCapabilities = BoardCapabilities {
+    audio_in_channels: 8,
+    audio_out_channels: 16,
+    ..CTAG_FACE
+};
+
+/// Combined with a Bela cape, the CTAG codecs follow the Bela sample rate
+const CTAG_FACE_BELA: BoardCapabilities = BoardCapabilities {
+    audio_in_channels: 6,
+    audio_out_channels: 10,
+    supports_audio_expander: false,
+    ..BELA
+};
+
+const CTAG_BEAST_BELA: BoardCapabilities = BoardCapabilities {
+    audio_in_channels: 10,
+    audio_out_channels: 18,
+    ..CTAG_FACE_BELA
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that channels are numbered in order and each GPIO and header
+    /// pin is used once
+    fn check_digital_pins(capabilities: &BoardCapabilities) {
+        let pins = capabilities.digital_pins;
+        for (index, pin) in pins.iter().enumerate() {
+            assert_eq!(pin.channel, index);
+            assert_eq!(capabilities.digital_pin_by_header(pin.header), Some(pin));
+            assert!(pins[..index].iter().all(|other| other.gpio != pin.gpio));
+            assert!(capabilities.has_gpio(pin.gpio));
+        }
+    }
+
+    #[test]
+    fn bela() {
+        let bela = BelaHw::Bela.capabilities();
+        assert_eq!((bela.audio_in_channels, bela.audio_out_channels), (2, 2));
+        assert_eq!((bela.analog_in_channels, bela.analog_out_channels), (8, 8));
+        assert_eq!(bela.digital_channels(), 16);
+        check_digital_pins(bela);
+        assert_eq!(bela.digital_pin(0).unwrap().header, "P8_07");
+        assert_eq!(bela.digital_pin(0).unwrap().gpio, 66);
+        assert_eq!(bela.digital_pin_by_header("P9_12").unwrap().channel, 6);
+        assert_eq!(bela.digital_pin(15).unwrap().gpio, 89);
+        assert!(bela.digital_pin(16).is_none());
+        assert_eq!(bela.led_pin, Some(LED_PIN));
+        assert!(bela.has_gpio(STOP_BUTTON_PIN) && bela.has_gpio(AMP_MUTE_PIN));
+        assert!(bela.has_gpio(LED_PIN));
+        assert!(!bela.has_gpio(5));
+        assert!(bela.supports_mux && bela.supports_audio_expander);
+    }
+
+    #[test]
+    fn bela_mini() {
+        let mini = BelaHw::BelaMini.capabilities();
+        assert_eq!((mini.audio_in_channels, mini.audio_out_channels), (2, 2));
+        assert_eq!((mini.analog_in_channels, mini.analog_out_channels), (8, 0));
+        assert_eq!(mini.digital_channels(), 16);
+        check_digital_pins(mini);
+        assert_eq!(mini.digital_pin(0).unwrap().header, "P2.01");
+        assert_eq!(mini.digital_pin_by_header("P1.06").unwrap().gpio, 5);
+        assert!(mini.digital_pin_by_header("P8_07").is_none());
+        assert_eq!(mini.stop_button_pin, Some(STOP_BUTTON_PIN));
+        assert_eq!(mini.amp_mute_pin, None);
+        assert_eq!(mini.led_pin, Some(LED_PIN));
+        assert!(!mini.has_gpio(AMP_MUTE_PIN) && !mini.has_gpio(66));
+        assert!(!mini.supports_mux);
+    }
+
+    #[test]
+    fn other_boards() {
+        for &board in &[
+            BelaHw::Salt,
+            BelaHw::CtagFace,
+            BelaHw::CtagBeast,
+            BelaHw::CtagFaceBela,
+            BelaHw::CtagBeastBela,
+        ] {
+            check_digital_pins(board.capabilities());
+        }
+        let beast = BelaHw::CtagBeast.capabilities();
+        assert_eq!((beast.audio_in_channels, beast.audio_out_channels), (8, 16));
+        assert_eq!(beast.analog_in_channels, 0);
+        assert_eq!(beast.audio_sample_rate, 48000.);
+        assert_eq!(
+            BelaHw::CtagBeastBela.capabilities().audio_sample_rate,
+            44100.
+        );
+        assert!(!beast.has_gpio(STOP_BUTTON_PIN));
+        assert_eq!(beast.led_pin, Some(LED_PIN));
+        for &board in &[BelaHw::Salt, BelaHw::CtagFaceBela, BelaHw::CtagBeastBela] {
+            assert_eq!(board.capabilities().led_pin, Some(LED_PIN));
+        }
+        let no_hw = BelaHw::NoHw.capabilities();
+        assert_eq!(no_hw.digital_channels(), 0);
+        assert_eq!(no_hw.led_pin, None);
+    }
+}
@@ -0,0 +1,460 @@
+use std::iter::StepBy;
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut, Range};
+use std::slice::{Iter, IterMut};
+
+use crate::RenderContext;
+
+/// Internal description of the memory layout of a buffer
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    frames: usize,
+    channels: usize,
+    interleaved: bool,
+}
+
+impl Layout {
+    /// Index of a sample, panicking if it is out of bounds
+    fn index(&self, frame: usize, channel: usize) -> usize {
+        assert!(
+            frame < self.frames && channel < self.channels,
+            "sample (frame {}, channel {}) out of bounds ({} frames, {} channels)",
+            frame,
+            channel,
+            self.frames,
+            self.channels
+        );
+        frame * self.frame_stride() + channel * self.channel_stride()
+    }
+
+    /// Distance between two consecutive frames of a channel
+    fn frame_stride(&self) -> usize {
+        if self.interleaved {
+            self.channels
+        } else {
+            1
+        }
+    }
+
+    /// Distance between two consecutive channels of a frame
+    fn channel_stride(&self) -> usize {
+        if self.interleaved {
+            1
+        } else {
+            self.frames
+        }
+    }
+
+    /// Range of samples containing a channel, and the step between them
+    fn channel_range(&self, channel: usize) -> (Range<usize>, usize) {
+        assert!(
+            channel < self.channels,
+            "channel {} out of bounds ({} channels)",
+            channel,
+            self.channels
+        );
+        if self.interleaved {
+            (
+                channel..(self.frames * self.channels).max(channel),
+                self.channels,
+            )
+        } else {
+            (channel * self.frames..(channel + 1) * self.frames, 1)
+        }
+    }
+}
+
+/// View of an audio or analog input buffer, indexed by frame and channel
+///
+/// Hides whether the samples are interleaved or not.
+pub struct Buffer<'a> {
+    data: &'a [f32],
+    layout: Layout,
+}
+
+impl<'a> Buffer<'a> {
+    pub(crate) fn new(data: &'a [f32], frames: usize, channels: usize, interleaved: bool) -> Self {
+        debug_assert_eq!(data.len(), frames * channels);
+        Self {
+            data,
+            layout: Layout {
+                frames,
+                channels,
+                interleaved,
+            },
+        }
+    }
+
+    /// Number of frames
+    pub fn num_frames(&self) -> usize {
+        self.layout.frames
+    }
+
+    /// Number of channels
+    pub fn num_channels(&self) -> usize {
+        self.layout.channels
+    }
+
+    /// Check if the underlying samples are interleaved
+    pub fn is_interleaved(&self) -> bool {
+        self.layout.interleaved
+    }
+
+    /// The underlying samples
+    pub fn as_slice(&self) -> &'a [f32] {
+        self.data
+    }
+
+    /// Read the sample of `channel` at `frame`
+    pub fn read(&self, frame: usize, channel: usize) -> f32 {
+        self.data[self.layout.index(frame, channel)]
+    }
+
+    /// Iterate over all samples of `channel`
+    pub fn channel(&self, channel: usize) -> StepBy<Iter<'a, f32>> {
+        let (range, step) = self.layout.channel_range(channel);
+        self.data[range].iter().step_by(step)
+    }
+
+    /// Iterate over all frames
+    pub fn frames(&self) -> impl Iterator<Item = Frame<'a>> + 'a {
+        let Self { data, layout } = *self;
+        (0..layout.frames).map(move |frame| Frame {
+            data: &data[frame * layout.frame_stride()..],
+            stride: layout.channel_stride(),
+            channels: layout.channels,
+        })
+    }
+}
+
+/// View of an audio or analog output buffer, indexed by frame and channel
+///
+/// Hides whether the samples are interleaved or not:
+///
+/// ```
+/// use bela::{RenderContext, TestRig};
+///
+/// let mut rig = TestRig::new().interleave(false);
+/// rig.render(1, |context: &mut RenderContext| {
+///     for mut frame in context.audio_out_view().frames_mut() {
+///         frame[1] = 1.;
+///     }
+/// });
+/// // captured outputs are always interleaved
+/// assert_eq!(rig.audio_out()[..4], [0., 1., 0., 1.]);
+/// ```
+pub struct BufferMut<'a> {
+    data: &'a mut [f32],
+    layout: Layout,
+}
+
+impl<'a> BufferMut<'a> {
+    pub(crate) fn new(
+        data: &'a mut [f32],
+        frames: usize,
+        channels: usize,
+        interleaved: bool,
+    ) -> Self {
+        debug_assert_eq!(data.len(), frames * channels);
+        Self {
+            data,
+            layout: Layout {
+                frames,
+                channels,
+                interleaved,
+            },
+        }
+    }
+
+    /// Number of frames
+    pub fn num_frames(&self) -> usize {
+        self.layout.frames
+    }
+
+    /// Number of channels
+    pub fn num_channels(&self) -> usize {
+        self.layout.channels
+    }
+
+    /// Check if the underlying samples are interleaved
+    pub fn is_interleaved(&self) -> bool {
+        self.layout.interleaved
+    }
+
+    /// The underlying samples
+    pub fn as_slice(&self) -> &[f32] {
+        self.data
+    }
+
+    /// The underlying samples, mutably
+    pub fn as_mut_slice(&mut self) -> &mut [f32] {
+        self.data
+    }
+
+    /// Read the sample of `channel` at `frame`
+    pub fn read(&self, frame: usize, channel: usize) -> f32 {
+        self.data[self.layout.index(frame, channel)]
+    }
+
+    /// Write the sample of `channel` at `frame`
+    pub fn write(&mut self, frame: usize, channel: usize, value: f32) {
+        self.data[self.layout.index(frame, channel)] = value;
+    }
+
+    /// Iterate over all samples of `channel`
+    pub fn channel(&self, channel: usize) -> StepBy<Iter<'_, f32>> {
+        let (range, step) = self.layout.channel_range(channel);
+        self.data[range].iter().step_by(step)
+    }
+
+    /// Iterate mutably over all samples of `channel`
+    pub fn channel_mut(&mut self, channel: usize) -> StepBy<IterMut<'_, f32>> {
+        let (range, step) = self.layout.channel_range(channel);
+        self.data[range].iter_mut().step_by(step)
+    }
+
+    /// Iterate mutably over all frames
+    pub fn frames_mut(&mut self) -> FramesMut<'_> {
+        FramesMut {
+            ptr: self.data.as_mut_ptr(),
+            layout: self.layout,
+            frame: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// A single frame of a `Buffer`
+pub struct Frame<'a> {
+    data: &'a [f32],
+    stride: usize,
+    channels: usize,
+}
+
+impl<'a> Frame<'a> {
+    /// Number of channels
+    pub fn num_channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Iterate over the samples of all channels
+    pub fn iter(&self) -> impl Iterator<Item = &'a f32> + 'a {
+        self.data.iter().step_by(self.stride).take(self.channels)
+    }
+}
+
+impl Index<usize> for Frame<'_> {
+    type Output = f32;
+
+    fn index(&self, channel: usize) -> &f32 {
+        assert!(channel < self.channels, "channel {} out of bounds", channel);
+        &self.data[channel * self.stride]
+    }
+}
+
+/// Iterator over the frames of a `BufferMut`, see `BufferMut::frames_mut`
+pub struct FramesMut<'a> {
+    ptr: *mut f32,
+    layout: Layout,
+    frame: usize,
+    _marker: PhantomData<&'a mut [f32]>,
+}
+
+impl<'a> Iterator for FramesMut<'a> {
+    type Item = FrameMut<'a>;
+
+    fn next(&mut self) -> Option<FrameMut<'a>> {
+        if self.frame == self.layout.frames {
+            return None;
+        }
+        // frames never share samples, so handing out several `FrameMut`
+        // borrowing the same buffer does not alias
+        let ptr = unsafe { self.ptr.add(self.frame * self.layout.frame_stride()) };
+        self.frame += 1;
+        Some(FrameMut {
+            ptr,
+            stride: self.layout.channel_stride(),
+            channels: self.layout.channels,
+            _marker: PhantomData,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.layout.frames - self.frame;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for FramesMut<'_> {}
+
+/// A single frame of a `BufferMut`
+pub struct FrameMut<'a> {
+    ptr: *mut f32,
+    stride: usize,
+    channels: usize,
+    _marker: PhantomData<&'a mut [f32]>,
+}
+
+impl FrameMut<'_> {
+    /// Number of channels
+    pub fn num_channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Iterate mutably over the samples of all channels
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut f32> + '_ {
+        let Self { ptr, stride, .. } = *self;
+        (0..self.channels).map(move |channel| unsafe { &mut *ptr.add(channel * stride) })
+    }
+
+    /// Set the samples of all channels to `value`
+    pub fn fill(&mut self, value: f32) {
+        for sample in self.iter_mut() {
+            *sample = value;
+        }
+    }
+}
+
+impl Index<usize> for FrameMut<'_> {
+    type Output = f32;
+
+    fn index(&self, channel: usize) -> &f32 {
+        assert!(channel < self.channels, "channel {} out of bounds", channel);
+        unsafe { &*self.ptr.add(channel * self.stride) }
+    }
+}
+
+impl IndexMut<usize> for FrameMut<'_> {
+    fn index_mut(&mut self, channel: usize) -> &mut f32 {
+        assert!(channel < self.channels, "channel {} out of bounds", channel);
+        unsafe { &mut *self.ptr.add(channel * self.stride) }
+    }
+}
+
+impl RenderContext {
+    /// View of the audio input buffer
+    pub fn audio_in_view(&self) -> Buffer<'_> {
+        let interleaved = self.flags().is_interleaved();
+        Buffer::new(
+            self.audio_in(),
+            self.audio_frames(),
+            self.audio_in_channels(),
+            interleaved,
+        )
+    }
+
+    /// View of the audio output buffer
+    pub fn audio_out_view(&mut self) -> BufferMut<'_> {
+        let (frames, channels) = (self.audio_frames(), self.audio_out_channels());
+        let interleaved = self.flags().is_interleaved();
+        BufferMut::new(self.audio_out(), frames, channels, interleaved)
+    }
+
+    /// View of the analog input buffer
+    pub fn analog_in_view(&self) -> Buffer<'_> {
+        let interleaved = self.flags().is_interleaved();
+        Buffer::new(
+            self.analog_in(),
+            self.analog_frames(),
+            self.analog_in_channels(),
+            interleaved,
+        )
+    }
+
+    /// View of the analog output buffer
+    pub fn analog_out_view(&mut self) -> BufferMut<'_> {
+        let (frames, channels) = (self.analog_frames(), self.analog_out_channels());
+        let interleaved = self.flags().is_interleaved();
+        BufferMut::new(self.analog_out(), frames, channels, interleaved)
+    }
+
+    /// Returns the value of a given audio input at the given frame number
+    pub fn audio_read(&self, frame: usize, channel: usize) -> f32 {
+        self.audio_in_view().read(frame, channel)
+    }
+
+    /// Sets a given audio output channel to a value for the given frame
+    pub fn audio_write(&mut self, frame: usize, channel: usize, value: f32) {
+        self.audio_out_view().write(frame, channel, value);
+    }
+
+    /// Returns the value of a given analog input at the given frame number
+    pub fn analog_read(&self, frame: usize, channel: usize) -> f32 {
+        self.analog_in_view().read(frame, channel)
+    }
+
+    /// Sets a given analog output channel to a value for the current frame
+    /// and, if analog outputs persist, all subsequent frames
+    ///
+    /// Mirrors `analogWrite` of the Bela C API. With persistence enabled
+    /// via `Bela::analog_outputs_persist`, the value is also held in
+    /// subsequent periods until it is written again:
+    ///
+    /// ```
+    /// use bela::{RenderContext, TestRig};
+    ///
+    /// let mut rig = TestRig::new().period_size(8).analog_outputs_persist(true);
+    /// rig.render(1, |context: &mut RenderContext| context.analog_write(4, 0, 0.5));
+    /// rig.render(1, |_context: &mut RenderContext| {});
+    /// let channels = 8;
+    /// assert_eq!(rig.analog_out()[3 * channels], 0.);
+    /// assert_eq!(rig.analog_out()[4 * channels], 0.5);
+    /// assert_eq!(rig.analog_out()[15 * channels], 0.5);
+    /// ```
+    ///
+    /// Panics if `frame` or `channel` is out of bounds.
+    pub fn analog_write(&mut self, frame: usize, channel: usize, value: f32) {
+        if self.flags().analog_outputs_persist() {
+            let mut analog_out = self.analog_out_view();
+            // the first write checks the bounds, like `analog_write_once`
+            analog_out.write(frame, channel, value);
+            for frame in frame + 1..analog_out.num_frames() {
+                analog_out.write(frame, channel, value);
+            }
+        } else {
+            self.analog_write_once(frame, channel, value);
+        }
+    }
+
+    /// Sets a given analog output channel to a value for the current frame
+    /// only
+    ///
+    /// Panics if `frame` or `channel` is out of bounds.
+    pub fn analog_write_once(&mut self, frame: usize, channel: usize, value: f32) {
+        self.analog_out_view().write(frame, channel, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RenderContext, TestRig};
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn analog_write_checks_frame() {
+        let mut rig = TestRig::new().period_size(8).analog_outputs_persist(true);
+        rig.render(1, |context: &mut RenderContext| {
+            context.analog_write(8, 0, 0.5)
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn analog_write_once_checks_frame() {
+        let mut rig = TestRig::new().period_size(8).analog_outputs_persist(true);
+        rig.render(1, |context: &mut RenderContext| {
+            context.analog_write_once(8, 0, 0.5)
+        });
+    }
+
+    #[test]
+    fn analog_write_last_frame() {
+        let mut rig = TestRig::new().period_size(8).analog_outputs_persist(true);
+        rig.render(1, |context: &mut RenderContext| {
+            context.analog_write(7, 1, 0.5)
+        });
+        let channels = 8;
+        assert_eq!(rig.analog_out()[6 * channels + 1], 0.);
+        assert_eq!(rig.analog_out()[7 * channels + 1], 0.5);
+    }
+}
@@ -0,0 +1,328 @@
+use crate::{DigitalIn, RenderContext};
+
+/// Maximum number of events reported per call to `Button::process`
+const MAX_EVENTS: usize = 16;
+
+/// Event reported by a `Button`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonEvent {
+    /// The button was pressed
+    Pressed,
+    /// The button was released
+    Released,
+    /// The button has been held for the long press time
+    LongPress,
+    /// The button was pressed again within the double click time after a
+    /// short press, reported after the corresponding `Pressed`
+    DoubleClick,
+}
+
+/// Debounced push button on a digital input
+///
+/// Created from a `DigitalIn` handle and driven once per period via
+/// `Button::process`, which reports events together with the digital
+/// frame at which they occurred. All state is preallocated, so
+/// processing is realtime safe. Synthetic input can be fed via
+/// `Button::process_frames`:
+///
+/// ```
+/// use bela::{Button, ButtonEvent, TestRig};
+///
+/// let mut rig = TestRig::new();
+/// let input = rig.setup(|context| context.digital_input(0)).unwrap();
+/// let mut button = Button::new(input).debounce_ms(2.);
+///
+/// // pressed from frame 10 on, at 1 kHz one frame lasts 1 ms
+/// let mut frames = [0u32; 32];
+/// frames[10..].iter_mut().for_each(|word| *word = 1 << 16);
+/// let events = button.process_frames(&frames, 1000.);
+/// assert_eq!(events, [(11, ButtonEvent::Pressed)]);
+/// ```
+#[derive(Debug)]
+pub struct Button {
+    input: DigitalIn,
+    active_low: bool,
+    debounce_ms: f32,
+    long_press_ms: f32,
+    double_click_ms: f32,
+    /// Debounced state, `true` while pressed
+    pressed: bool,
+    /// Number of consecutive frames differing from the debounced state
+    bouncing: usize,
+    /// Number of frames the button has been held
+    held: usize,
+    long_press_reported: bool,
+    /// Number of frames since the last short press was released, `None`
+    /// once the double click time has passed
+    since_click: Option<usize>,
+    events: [(usize, ButtonEvent); MAX_EVENTS],
+    num_events: usize,
+}
+
+/// Internal helper converting milliseconds to frames
+fn ms_to_frames(ms: f32, sample_rate: f32) -> usize {
+    (ms * sample_rate / 1000.).round().max(0.) as usize
+}
+
+impl Button {
+    /// Create a new `Button` with a debounce time of 10 ms, a long press
+    /// time of 500 ms and a double click time of 300 ms. The button is
+    /// considered pressed while the input is high.
+    pub fn new(input: DigitalIn) -> Self {
+        Self {
+            input,
+            active_low: false,
+            debounce_ms: 10.,
+            long_press_ms: 500.,
+            double_click_ms: 300.,
+            pressed: false,
+            bouncing: 0,
+            held: 0,
+            long_press_reported: false,
+            since_click: None,
+            events: [(0, ButtonEvent::Pressed); MAX_EVENTS],
+            num_events: 0,
+        }
+    }
+
+    /// Set the time the input has to be stable before a change is
+    /// reported
+    ///
+    /// The change is reported at the last frame of the debounce time, not
+    /// at the edge, so a press at frame `n` with a debounce time of 2
+    /// frames is reported at frame `n + 1`.
+    pub fn debounce_ms(mut self, ms: f32) -> Self {
+        self.debounce_ms = ms;
+        self
+    }
+
+    /// Set the time the button has to be held to report a long press
+    pub fn long_press_ms(mut self, ms: f32) -> Self {
+        self.long_press_ms = ms;
+        self
+    }
+
+    /// Set the maximum time between releasing and pressing the button
+    /// again to report a double click
+    pub fn double_click_ms(mut self, ms: f32) -> Self {
+        self.double_click_ms = ms;
+        self
+    }
+
+    /// Set whether the button is pressed while the input is low, e.g.,
+    /// when using a pull-up resistor
+    pub fn active_low(mut self, val: bool) -> Self {
+        self.active_low = val;
+        self
+    }
+
+    /// The underlying digital input
+    pub fn input(&self) -> &DigitalIn {
+        &self.input
+    }
+
+    /// Check if the button is currently pressed (debounced)
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Events reported by the last call to `Button::process`
+    pub fn events(&self) -> &[(usize, ButtonEvent)] {
+        &self.events[..self.num_events]
+    }
+
+    /// Process the digital frames of the current period, returning all
+    /// events with the digital frame at which they were detected
+    ///
+    /// Presses and releases are detected at the end of the debounce time,
+    /// see `Button::debounce_ms`. Long presses and double clicks are timed
+    /// from the detected presses and releases.
+    ///
+    /// At most 16 events are reported per period, further events are
+    /// dropped.
+    pub fn process(&mut self, context: &RenderContext) -> &[(usize, ButtonEvent)] {
+        self.process_frames(context.digital(), context.digital_sample_rate())
+    }
+
+    /// Process raw digital words as found in `RenderContext::digital`,
+    /// see `Button::process`
+    pub fn process_frames(&mut self, frames: &[u32], sample_rate: f32) -> &[(usize, ButtonEvent)] {
+        let debounce = ms_to_frames(self.debounce_ms, sample_rate);
+        let long_press = ms_to_frames(self.long_press_ms, sample_rate);
+        let double_click = ms_to_frames(self.double_click_ms, sample_rate);
+        let bit = self.input.channel() + 16;
+
+        self.num_events = 0;
+        for (frame, word) in frames.iter().enumerate() {
+            // the double click time includes the debounce time of the press
+            if let (false, Some(since_click)) = (self.pressed, &mut self.since_click) {
+                *since_click += 1;
+                if *since_click > double_click {
+                    self.since_click = None;
+                }
+            }
+
+            let pressed = ((word >> bit) & 1 != 0) != self.active_low;
+            if pressed != self.pressed {
+                self.bouncing += 1;
+                if self.bouncing >= debounce {
+                    self.bouncing = 0;
+                    self.pressed = pressed;
+                    if pressed {
+                        self.press(frame, double_click);
+                    } else {
+                        self.release(frame);
+                    }
+                    continue;
+                }
+            } else {
+                self.bouncing = 0;
+            }
+
+            if self.pressed {
+                self.held = self.held.saturating_add(1);
+                if !self.long_press_reported && self.held >= long_press {
+                    self.long_press_reported = true;
+                    self.push(frame, ButtonEvent::LongPress);
+                }
+            }
+        }
+
+        &self.events[..self.num_events]
+    }
+
+    fn press(&mut self, frame: usize, double_click: usize) {
+        self.held = 0;
+        self.long_press_reported = false;
+        self.push(frame, ButtonEvent::Pressed);
+        if matches!(self.since_click.take(), Some(since) if since <= double_click) {
+            self.push(frame, ButtonEvent::DoubleClick);
+        }
+    }
+
+    fn release(&mut self, frame: usize) {
+        // long presses do not count towards double clicks
+        self.since_click = if self.long_press_reported {
+            None
+        } else {
+            Some(0)
+        };
+        self.push(frame, ButtonEvent::Released);
+    }
+
+    fn push(&mut self, frame: usize, event: ButtonEvent) {
+        if let Some(slot) = self.events.get_mut(self.num_events) {
+            *slot = (frame, event);
+            self.num_events += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rig::{digital_inputs, digital_words};
+
+    /// Button on channel 3 with a debounce time of 2 frames at 1 kHz
+    fn button() -> Button {
+        let [input] = digital_inputs([3]);
+        Button::new(input).debounce_ms(2.)
+    }
+
+    fn process(button: &mut Button, levels: &str) -> Vec<(usize, ButtonEvent)> {
+        button
+            .process_frames(&digital_words(&[3], levels), 1000.)
+            .to_vec()
+    }
+
+    #[test]
+    fn reports_release() {
+        let mut button = button();
+        assert_eq!(
+            process(&mut button, "0011110000"),
+            [(3, ButtonEvent::Pressed), (7, ButtonEvent::Released)]
+        );
+        assert!(!button.is_pressed());
+    }
+
+    #[test]
+    fn ignores_bounces() {
+        let mut button = button().debounce_ms(3.);
+        assert_eq!(
+            process(&mut button, "0101101100111"),
+            [(12, ButtonEvent::Pressed)]
+        );
+        assert!(button.is_pressed());
+    }
+
+    #[test]
+    fn debounces_across_periods() {
+        let mut button = button().debounce_ms(4.);
+        assert_eq!(process(&mut button, "000011"), []);
+        assert_eq!(process(&mut button, "1100"), [(1, ButtonEvent::Pressed)]);
+        // a bounce at the boundary restarts the debounce time
+        assert_eq!(process(&mut button, "100000"), [(4, ButtonEvent::Released)]);
+        assert_eq!(button.events(), [(4, ButtonEvent::Released)]);
+    }
+
+    #[test]
+    fn reports_long_press() {
+        let mut button = button().long_press_ms(5.).double_click_ms(10.);
+        let events = process(&mut button, &format!("{}{}", "1".repeat(10), "0".repeat(4)));
+        assert_eq!(
+            events,
+            [
+                (1, ButtonEvent::Pressed),
+                (6, ButtonEvent::LongPress),
+                (11, ButtonEvent::Released)
+            ]
+        );
+        // long presses do not count towards double clicks
+        assert_eq!(process(&mut button, "11"), [(1, ButtonEvent::Pressed)]);
+    }
+
+    #[test]
+    fn reports_double_click() {
+        let mut button = button().double_click_ms(4.);
+        assert_eq!(
+            process(&mut button, "1110001100"),
+            [
+                (1, ButtonEvent::Pressed),
+                (4, ButtonEvent::Released),
+                (7, ButtonEvent::Pressed),
+                (7, ButtonEvent::DoubleClick),
+                (9, ButtonEvent::Released)
+            ]
+        );
+    }
+
+    #[test]
+    fn double_click_times_out() {
+        let is_double_click = |levels: &str| {
+            let mut button = button().double_click_ms(4.);
+            let events = process(&mut button, levels);
+            events.contains(&(events[2].0, ButtonEvent::DoubleClick))
+        };
+        // released at frame 4, pressed again 4 or 5 frames later
+        assert!(is_double_click("111000011100"));
+        assert!(!is_double_click("111000001100"));
+    }
+
+    #[test]
+    fn active_low() {
+        let mut button = button().active_low(true);
+        assert_eq!(
+            process(&mut button, "1100011"),
+            [(3, ButtonEvent::Pressed), (6, ButtonEvent::Released)]
+        );
+    }
+
+    #[test]
+    fn zero_debounce_reports_edge() {
+        let mut button = button().debounce_ms(0.);
+        assert_eq!(
+            process(&mut button, "0010"),
+            [(2, ButtonEvent::Pressed), (3, ButtonEvent::Released)]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use std::ffi::CString;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::platform::{Current, Platform};
+use crate::{AuxiliaryTask, Error, SetupContext};
+
+/// Priority of the auxiliary task applying codec changes
+const CODEC_TASK_PRIORITY: i32 = 10;
+/// Number of codec settings that can be changed at runtime
+const SLOTS: usize = 6;
+/// Slot indices, in the order in which pending changes are applied
+const DAC_LEVEL: usize = 0;
+const ADC_LEVEL: usize = 1;
+const PGA_GAIN_LEFT: usize = 2;
+const PGA_GAIN_RIGHT: usize = 3;
+const HEADPHONE_LEVEL: usize = 4;
+const MUTE_SPEAKERS: usize = 5;
+
+/// A single change of the codec settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CodecCommand {
+    /// Set the audio DAC level in dB
+    DacLevel(f32),
+    /// Set the audio ADC level in dB
+    AdcLevel(f32),
+    /// Set the PGA gain of a channel (0 = left, 1 = right) in dB
+    PgaGain { channel: usize, gain: f32 },
+    /// Set the headphone level in dB
+    HeadphoneLevel(f32),
+    /// Mute or unmute the speakers
+    MuteSpeakers(bool),
+}
+
+/// Backend applying codec commands, called from a non-realtime thread
+pub trait CodecBackend: Send + 'static {
+    /// Apply a single command
+    fn apply(&mut self, command: CodecCommand) -> Result<(), Error>;
+}
+
+/// `CodecBackend` applying commands to the Bela audio codec
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BelaCodec;
+
+impl CodecBackend for BelaCodec {
+    fn apply(&mut self, command: CodecCommand) -> Result<(), Error> {
+        let res = match command {
+            CodecCommand::DacLevel(level) => Current::set_dac_level(level),
+            CodecCommand::AdcLevel(level) => Current::set_adc_level(level),
+            CodecCommand::PgaGain { channel, gain } => Current::set_pga_gain(gain, channel as _),
+            CodecCommand::HeadphoneLevel(level) => Current::set_headphone_level(level),
+            CodecCommand::MuteSpeakers(mute) => Current::mute_speakers(mute),
+        };
+
+        match res {
+            0 => Ok(()),
+            _ => Err(Error::Codec),
+        }
+    }
+}
+
+/// `CodecBackend` recording all applied commands, for testing off-device
+///
+/// Clones share the same record, so a clone can be handed to
+/// `CodecControl` while the original is used for assertions.
+#[derive(Debug, Default, Clone)]
+pub struct StubCodec {
+    applied: Arc<Mutex<Vec<CodecCommand>>>,
+}
+
+impl StubCodec {
+    /// Create a new `StubCodec` without any recorded commands
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All commands applied so far, in order
+    pub fn applied(&self) -> Vec<CodecCommand> {
+        self.applied.lock().unwrap().clone()
+    }
+}
+
+impl CodecBackend for StubCodec {
+    fn apply(&mut self, command: CodecCommand) -> Result<(), Error> {
+        self.applied.lock().unwrap().push(command);
+        Ok(())
+    }
+}
+
+/// Internal state shared between `CodecControl` and its auxiliary task
+struct Shared {
+    /// Latest requested value of each setting, as `f32` bits
+    values: [AtomicU32; SLOTS],
+    /// Bit mask of settings changed since they were last applied
+    pending: AtomicU32,
+    /// Number of commands rejected by the backend
+    errors: AtomicUsize,
+    backend: Mutex<Box<dyn CodecBackend>>,
+}
+
+impl Shared {
+    fn request(&self, slot: usize, value: f32) {
+        self.values[slot].store(value.to_bits(), Ordering::Relaxed);
+        self.pending.fetch_or(1 << slot, Ordering::Release);
+    }
+
+    fn process(&self) -> Result<(), Error> {
+        let mut backend = self.backend.lock().unwrap();
+        let pending = self.pending.swap(0, Ordering::Acquire);
+        let mut result = Ok(());
+        for slot in (0..SLOTS).filter(|slot| pending & (1 << slot) != 0) {
+            let value = f32::from_bits(self.values[slot].load(Ordering::Relaxed));
+            let command = match slot {
+                DAC_LEVEL => CodecCommand::DacLevel(value),
+                ADC_LEVEL => CodecCommand::AdcLevel(value),
+                PGA_GAIN_LEFT => CodecCommand::PgaGain {
+                    channel: 0,
+                    gain: value,
+                },
+                PGA_GAIN_RIGHT => CodecCommand::PgaGain {
+                    channel: 1,
+                    gain: value,
+                },
+                HEADPHONE_LEVEL => CodecCommand::HeadphoneLevel(value),
+                _ => CodecCommand::MuteSpeakers(value != 0.),
+            };
+            if let Err(err) = backend.apply(command) {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                result = Err(err);
+            }
+        }
+        result
+    }
+}
+
+/// Handle to change codec settings while running
+///
+/// Created via `SetupContext::codec_control`. All setters are realtime
+/// safe and may be called from `render`: they only store the requested
+/// value and schedule an auxiliary task, which performs the actual codec
+/// calls. Requests are coalesced, i.e., if a setting is changed several
+/// times before the task runs, only the latest value is applied.
+///
+/// Handles created via `CodecControl::manual` have no auxiliary task.
+/// Pending changes are instead applied by calling `CodecControl::process`,
+/// which together with `StubCodec` allows testing without a Bela:
+///
+/// ```
+/// use bela::{CodecCommand, CodecControl, StubCodec};
+///
+/// let stub = StubCodec::new();
+/// let codec = CodecControl::manual(stub.clone());
+/// codec.set_dac_level(-6.).unwrap();
+/// codec.set_dac_level(-3.).unwrap();
+/// codec.mute_speakers(true).unwrap();
+/// codec.process().unwrap();
+/// assert_eq!(
+///     stub.applied(),
+///     [CodecCommand::DacLevel(-3.), CodecCommand::MuteSpeakers(true)]
+/// );
+/// ```
+pub struct CodecControl {
+    shared: Arc<Shared>,
+    task: Option<AuxiliaryTask>,
+}
+
+impl CodecControl {
+    /// Create a handle without an auxiliary task, see `CodecControl::process`
+    pub fn manual(backend: impl CodecBackend) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                values: Default::default(),
+                pending: AtomicU32::new(0),
+                errors: AtomicUsize::new(0),
+                backend: Mutex::new(Box::new(backend)),
+            }),
+            task: None,
+        }
+    }
+
+    /// Internal helper storing a request and scheduling the task
+    fn request(&self, slot: usize, value: f32) -> Result<(), Error> {
+        self.shared.request(slot, value);
+        match &self.task {
+            Some(task) => task.schedule(),
+            None => Ok(()),
+        }
+    }
+
+    /// Set the audio DAC level in dB
+    pub fn set_dac_level(&self, level: f32) -> Result<(), Error> {
+        self.request(DAC_LEVEL, level)
+    }
+
+    /// Set the audio ADC level in dB
+    pub fn set_adc_level(&self, level: f32) -> Result<(), Error> {
+        self.request(ADC_LEVEL, level)
+    }
+
+    /// Set the PGA gain of a channel (0 = left, 1 = right) in dB
+    pub fn set_pga_gain(&self, channel: usize, gain: f32) -> Result<(), Error> {
+        match channel {
+            0 => self.request(PGA_GAIN_LEFT, gain),
+            1 => self.request(PGA_GAIN_RIGHT, gain),
+            _ => Err(Error::Codec),
+        }
+    }
+
+    /// Set the headphone level in dB
+    pub fn set_headphone_level(&self, level: f32) -> Result<(), Error> {
+        self.request(HEADPHONE_LEVEL, level)
+    }
+
+    /// Mute or unmute the speakers
+    pub fn mute_speakers(&self, mute: bool) -> Result<(), Error> {
+        self.request(MUTE_SPEAKERS, mute as u32 as f32)
+    }
+
+    /// Apply all pending changes on the calling thread
+    ///
+    /// Not realtime safe. Only needed for handles created via
+    /// `CodecControl::manual`. Returns `Error::Codec` if the backend
+    /// rejected any change.
+    pub fn process(&self) -> Result<(), Error> {
+        self.shared.process()
+    }
+
+    /// Number of changes rejected by the backend so far
+    pub fn errors(&self) -> usize {
+        self.shared.errors.load(Ordering::Relaxed)
+    }
+}
+
+impl SetupContext {
+    /// Create a `CodecControl` handle changing the Bela audio codec
+    pub fn codec_control(&mut self) -> Result<CodecControl, Error> {
+        self.codec_control_with(BelaCodec)
+    }
+
+    /// Create a `CodecControl` handle applying changes using `backend`
+    pub fn codec_control_with(
+        &mut self,
+        backend: impl CodecBackend,
+    ) -> Result<CodecControl, Error> {
+        static TASKS: AtomicUsize = AtomicUsize::new(0);
+
+        let mut control = CodecControl::manual(backend);
+        let shared = control.shared.clone();
+        // includes the process id, as task names must be unique across
+        // all Xenomai processes
+        let name = format!(
+            "bela-rs-codec-{}-{}",
+            std::process::id(),
+            TASKS.fetch_add(1, Ordering::Relaxed)
+        );
+        let name = CString::new(name).map_err(|_| Error::CreateTask)?;
+        let task = unsafe {
+            self.create_auxiliary_task(
+                Box::new(move || {
+                    let _ = shared.process();
+                }),
+                CODEC_TASK_PRIORITY,
+                &name,
+            )?
+        };
+        control.task = Some(task);
+        Ok(control)
+    }
+}
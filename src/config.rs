@@ -0,0 +1,420 @@
+use crate::platform::{Current, Platform};
+use crate::BelaHw;
+
+/// Valid numbers of analog channels
+const ANALOG_CHANNELS: &[usize] = &[0, 2, 4, 8];
+/// Valid numbers of multiplexer channels (0 disables the multiplexer)
+const MUX_CHANNELS: &[usize] = &[0, 2, 4, 8];
+/// Minimum and maximum period size
+const PERIOD_SIZE: (usize, usize) = (2, 128);
+/// Highest valid GPIO pin number
+const MAX_PIN: i64 = 127;
+
+/// A single problem found while validating `Bela` settings
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ConfigIssue {
+    /// A value does not fit into the corresponding Bela setting
+    Overflow { setting: &'static str, value: usize },
+    /// The period size is not a power of two within the supported range
+    PeriodSize(usize),
+    /// A channel count is not one of the supported values
+    InvalidChannelCount {
+        setting: &'static str,
+        value: usize,
+        allowed: &'static [usize],
+    },
+    /// More channels were requested than the selected board provides
+    TooManyChannels {
+        setting: &'static str,
+        requested: usize,
+        available: usize,
+    },
+    /// A level or gain is not finite or outside its valid range
+    LevelOutOfRange {
+        setting: &'static str,
+        value: f32,
+        min: f32,
+        max: f32,
+    },
+    /// A pin number is outside the valid range
+    InvalidPin { setting: &'static str, pin: i64 },
+    /// A pin is not available on the selected board
+    PinNotOnBoard {
+        setting: &'static str,
+        pin: i64,
+        board: BelaHw,
+    },
+    /// The multiplexer capelet is not supported by the selected board
+    MuxNotSupported(BelaHw),
+    /// The PRU number is neither 0 nor 1
+    InvalidPruNumber(i64),
+    /// The selected board is not known
+    UnknownBoard(i64),
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            ConfigIssue::Overflow { setting, value } => {
+                write!(f, "{}: value {} is too large", setting, value)
+            }
+            ConfigIssue::PeriodSize(size) => write!(
+                f,
+                "period_size: {} is not a power of two between {} and {}",
+                size, PERIOD_SIZE.0, PERIOD_SIZE.1
+            ),
+            ConfigIssue::InvalidChannelCount {
+                setting,
+                value,
+                allowed,
+            } => write!(f, "{}: {} is not one of {:?}", setting, value, allowed),
+            ConfigIssue::TooManyChannels {
+                setting,
+                requested,
+                available,
+            } => write!(
+                f,
+                "{}: {} channels requested, but only {} are available",
+                setting, requested, available
+            ),
+            ConfigIssue::LevelOutOfRange {
+                setting,
+                value,
+                min,
+                max,
+            } => write!(
+                f,
+                "{}: {} dB is outside the range {} dB to {} dB",
+                setting, value, min, max
+            ),
+            ConfigIssue::InvalidPin { setting, pin } => {
+                write!(f, "{}: {} is not a valid pin (0-{})", setting, pin, MAX_PIN)
+            }
+            ConfigIssue::PinNotOnBoard {
+                setting,
+                pin,
+                board,
+            } => write!(
+                f,
+                "{}: pin {} is not available on {:?}",
+                setting, pin, board
+            ),
+            ConfigIssue::MuxNotSupported(board) => write!(
+                f,
+                "num_mux_channels: the multiplexer capelet is not supported on {:?}",
+                board
+            ),
+            ConfigIssue::InvalidPruNumber(pru) => {
+                write!(f, "pru_number: {} is neither 0 nor 1", pru)
+            }
+            ConfigIssue::UnknownBoard(board) => write!(f, "board: {} is not known", board),
+        }
+    }
+}
+
+/// Error returned by `Bela::build`, listing every problem found in the
+/// settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    issues: Vec<ConfigIssue>,
+}
+
+impl ConfigError {
+    /// All problems found in the settings
+    pub fn issues(&self) -> &[ConfigIssue] {
+        &self.issues
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "invalid Bela settings:")?;
+        for issue in &self.issues {
+            write!(f, "\n  {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Internal function validating `settings`, returning all problems found
+/// in addition to the already known `issues`
+pub(crate) fn validate(
+    settings: &bela_sys::BelaInitSettings,
+    issues: Vec<ConfigIssue>,
+) -> Result<(), ConfigError> {
+    validate_with(settings, Current::detect_hw, issues)
+}
+
+/// Internal function validating `settings`, using `detect_hw` to find the
+/// board if none is selected
+fn validate_with(
+    settings: &bela_sys::BelaInitSettings,
+    detect_hw: impl FnOnce() -> bela_sys::BelaHw,
+    mut issues: Vec<ConfigIssue>,
+) -> Result<(), ConfigError> {
+    let period_size = settings.periodSize as usize;
+    if !period_size.is_power_of_two() || !(PERIOD_SIZE.0..=PERIOD_SIZE.1).contains(&period_size) {
+        issues.push(ConfigIssue::PeriodSize(period_size));
+    }
+
+    // settings.board is the requested board, `NoHw` means auto-detection
+    let board = match BelaHw::from_raw(settings.board as _) {
+        Some(BelaHw::NoHw) => BelaHw::from_raw(detect_hw()).filter(|&board| board != BelaHw::NoHw),
+        Some(board) => Some(board),
+        None => {
+            issues.push(ConfigIssue::UnknownBoard(settings.board as _));
+            None
+        }
+    };
+    // without a known board, e.g., off-device, the limits of the original
+    // Bela are used
+    let capabilities = board.unwrap_or(BelaHw::Bela).capabilities();
+    let analog_in = capabilities.analog_in_channels;
+    let analog_out = capabilities.analog_out_channels;
+    let digital = capabilities.digital_channels();
+    let mut check_channels =
+        |setting, requested: usize, allowed: Option<&'static [usize]>, available: usize| {
+            if let Some(allowed) = allowed.filter(|allowed| !allowed.contains(&requested)) {
+                issues.push(ConfigIssue::InvalidChannelCount {
+                    setting,
+                    value: requested,
+                    allowed,
+                });
+            } else if requested > available {
+                issues.push(ConfigIssue::TooManyChannels {
+                    setting,
+                    requested,
+                    available,
+                });
+            }
+        };
+    // boards without analog inputs or outputs simply ignore the settings
+    if settings.useAnalog != 0 && analog_in > 0 {
+        check_channels(
+            "num_analog_in_channels",
+            settings.numAnalogInChannels as _,
+            Some(ANALOG_CHANNELS),
+            analog_in,
+        );
+    }
+    if settings.useAnalog != 0 && analog_out > 0 {
+        check_channels(
+            "num_analog_out_channels",
+            settings.numAnalogOutChannels as _,
+            Some(ANALOG_CHANNELS),
+            analog_out,
+        );
+    }
+    if settings.useDigital != 0 {
+        check_channels(
+            "num_digital_channels",
+            settings.numDigitalChannels as _,
+            None,
+            digital,
+        );
+    }
+    check_channels(
+        "num_mux_channels",
+        settings.numMuxChannels as _,
+        Some(MUX_CHANNELS),
+        usize::MAX,
+    );
+    if let Some(board) = board.filter(|board| !board.capabilities().supports_mux) {
+        if settings.numMuxChannels != 0 {
+            issues.push(ConfigIssue::MuxNotSupported(board));
+        }
+    }
+
+    let mut check_level = |setting, value: f32, min: f32, max: f32| {
+        // also catches NaN
+        if !(min..=max).contains(&value) {
+            issues.push(ConfigIssue::LevelOutOfRange {
+                setting,
+                value,
+                min,
+                max,
+            });
+        }
+    };
+    check_level("dac_level", settings.dacLevel, -63.5, 0.);
+    check_level("adc_level", settings.adcLevel, -12., 0.);
+    check_level("pga_gain", settings.pgaGain[0], 0., 59.5);
+    check_level("pga_gain", settings.pgaGain[1], 0., 59.5);
+    check_level("headphone_level", settings.headphoneLevel, -63.5, 0.);
+
+    let mut check_pin = |setting, pin: i64, board_pin: Option<u8>| {
+        // -1 disables the pin
+        if !(-1..=MAX_PIN).contains(&pin) {
+            issues.push(ConfigIssue::InvalidPin { setting, pin });
+            return;
+        }
+        // boards without a button or amplifiers simply ignore the setting,
+        // otherwise any GPIO of the selected board may be used
+        if let Some(board) = board.filter(|_| pin != -1 && board_pin.is_some()) {
+            if !board.capabilities().has_gpio(pin as _) {
+                issues.push(ConfigIssue::PinNotOnBoard {
+                    setting,
+                    pin,
+                    board,
+                });
+            }
+        }
+    };
+    check_pin(
+        "stop_button_pin",
+        settings.stopButtonPin as _,
+        capabilities.stop_button_pin,
+    );
+    check_pin(
+        "amp_mute_pin",
+        settings.ampMutePin as _,
+        capabilities.amp_mute_pin,
+    );
+
+    let pru_number = settings.pruNumber as i64;
+    if pru_number != 0 && pru_number != 1 {
+        issues.push(ConfigIssue::InvalidPruNumber(pru_number));
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigError { issues })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::owned_context::default_settings;
+
+    fn issues(
+        configure: impl FnOnce(&mut bela_sys::BelaInitSettings),
+        detected: BelaHw,
+    ) -> Vec<ConfigIssue> {
+        let mut settings = default_settings();
+        configure(&mut settings);
+        match validate_with(&settings, || detected as _, Vec::new()) {
+            Ok(()) => Vec::new(),
+            Err(err) => err.issues().to_vec(),
+        }
+    }
+
+    #[test]
+    fn accepts_defaults() {
+        assert_eq!(issues(|_| {}, BelaHw::Bela), []);
+        assert_eq!(issues(|_| {}, BelaHw::NoHw), []);
+    }
+
+    #[test]
+    fn reports_all_issues() {
+        let found = issues(
+            |settings| {
+                settings.periodSize = 24;
+                settings.numAnalogInChannels = 3;
+                settings.dacLevel = f32::NAN;
+                settings.pruNumber = 2;
+            },
+            BelaHw::Bela,
+        );
+        assert_eq!(found.len(), 4);
+        assert_eq!(found[0], ConfigIssue::PeriodSize(24));
+        assert!(matches!(
+            found[1],
+            ConfigIssue::InvalidChannelCount {
+                setting: "num_analog_in_channels",
+                value: 3,
+                ..
+            }
+        ));
+        assert!(matches!(
+            found[2],
+            ConfigIssue::LevelOutOfRange {
+                setting: "dac_level",
+                ..
+            }
+        ));
+        assert_eq!(found[3], ConfigIssue::InvalidPruNumber(2));
+    }
+
+    #[test]
+    fn keeps_known_issues() {
+        let known = ConfigIssue::Overflow {
+            setting: "period_size",
+            value: usize::MAX,
+        };
+        let settings = default_settings();
+        let err = validate_with(&settings, || BelaHw::Bela as _, vec![known.clone()]);
+        assert_eq!(err.unwrap_err().issues(), [known]);
+    }
+
+    #[test]
+    fn checks_selected_board() {
+        let board = |board: BelaHw| {
+            move |settings: &mut bela_sys::BelaInitSettings| {
+                settings.board = board as _;
+                settings.numDigitalChannels = 17;
+            }
+        };
+        // an explicitly selected board takes precedence over detection
+        let found = issues(board(BelaHw::BelaMini), BelaHw::CtagFace);
+        assert_eq!(
+            found,
+            [ConfigIssue::TooManyChannels {
+                setting: "num_digital_channels",
+                requested: 17,
+                available: 16,
+            }]
+        );
+        assert_eq!(
+            issues(|settings| settings.board = 42, BelaHw::Bela),
+            [ConfigIssue::UnknownBoard(42)]
+        );
+    }
+
+    #[test]
+    fn resolves_detected_board() {
+        // GPIO 66 is digital channel 0 on Bela, but not available on BelaMini
+        let stop_button = |settings: &mut bela_sys::BelaInitSettings| settings.stopButtonPin = 66;
+        assert_eq!(issues(stop_button, BelaHw::Bela), []);
+        assert_eq!(
+            issues(stop_button, BelaHw::BelaMini),
+            [ConfigIssue::PinNotOnBoard {
+                setting: "stop_button_pin",
+                pin: 66,
+                board: BelaHw::BelaMini,
+            }]
+        );
+        // without a detected board, only the Bela limits are checked
+        assert_eq!(issues(stop_button, BelaHw::NoHw), []);
+        assert!(matches!(
+            issues(|settings| settings.numAnalogInChannels = 16, BelaHw::NoHw)[..],
+            [ConfigIssue::InvalidChannelCount { .. }]
+        ));
+    }
+
+    #[test]
+    fn checks_mux_support() {
+        let mux = |settings: &mut bela_sys::BelaInitSettings| settings.numMuxChannels = 2;
+        assert_eq!(issues(mux, BelaHw::Bela), []);
+        // also reported when the board is detected rather than selected
+        assert_eq!(
+            issues(mux, BelaHw::BelaMini),
+            [ConfigIssue::MuxNotSupported(BelaHw::BelaMini)]
+        );
+        assert_eq!(
+            issues(
+                |settings| {
+                    mux(settings);
+                    settings.board = BelaHw::CtagFace as _;
+                },
+                BelaHw::Bela
+            ),
+            [ConfigIssue::MuxNotSupported(BelaHw::CtagFace)]
+        );
+        assert_eq!(issues(|_| {}, BelaHw::BelaMini), []);
+    }
+}
@@ -1,10 +1,14 @@
 use std::marker::PhantomData;
 use std::slice::{from_raw_parts, from_raw_parts_mut};
 
+use crate::BelaFlags;
+
 /// Tag type to mark Bela contexts within `setup`
 pub struct SetupTag;
 /// Tag type to mark Bela contexts within `render`
 pub struct RenderTag;
+/// Tag type to mark Bela contexts within `cleanup`
+pub struct CleanupTag;
 
 /// Bela context passed to setup/render/cleanup-functions.
 /// `StateTag` represents the current state of the Bela application
@@ -14,6 +18,8 @@ pub struct Context<StateTag>(*mut bela_sys::BelaContext, PhantomData<StateTag>);
 pub type SetupContext = Context<SetupTag>;
 /// Type alias for a Bela context within `render`
 pub type RenderContext = Context<RenderTag>;
+/// Type alias for a Bela context within `cleanup`
+pub type CleanupContext = Context<CleanupTag>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DigitalDirection {
@@ -27,9 +33,10 @@ impl<StateTag> Context<StateTag> {
     ///
     /// # Safety
     /// The correct type state tag must be passed, i.e., `SetupTag` within
-    /// `setup` and `RenderTag` within `render`. Furthermore, this object should
-    /// not escape the confines of the containing `setup`/`render` and should
-    /// therefore only be passed by mutable reference to user code
+    /// `setup`, `RenderTag` within `render` and `CleanupTag` within
+    /// `cleanup`. Furthermore, this object should not escape the confines
+    /// of the containing `setup`/`render`/`cleanup` and should therefore
+    /// only be passed by mutable reference to user code
     pub(crate) unsafe fn new(context: *mut bela_sys::BelaContext) -> Self {
         Self(context, PhantomData)
     }
@@ -87,10 +94,14 @@ impl<StateTag> Context<StateTag> {
         self.raw().audioFramesElapsed as _
     }
 
+    /// Number of multiplexer channels per analog input, 0 if the
+    /// multiplexer is disabled
     pub fn multiplexer_channels(&self) -> usize {
         self.raw().multiplexerChannels as _
     }
 
+    /// Multiplexer channel sampled at the first analog frame of the
+    /// period, advancing by one channel per analog frame
     pub fn multiplexer_starting_channel(&self) -> usize {
         self.raw().multiplexerStartingChannel as _
     }
@@ -99,8 +110,8 @@ impl<StateTag> Context<StateTag> {
         self.raw().audioExpanderEnabled
     }
 
-    pub fn flags(&self) -> u32 {
-        self.raw().flags
+    pub fn flags(&self) -> BelaFlags {
+        BelaFlags::from_bits(self.raw().flags)
     }
 }
 
@@ -109,7 +120,7 @@ impl SetupContext {}
 
 // functions for render contexts only
 impl RenderContext {
-    /// Access the audio output slice
+    /// Access the audio output slice, interleaved if `flags().is_interleaved()`
     pub fn audio_out(&mut self) -> &mut [f32] {
         let n_frames = self.audio_frames();
         let n_channels = self.audio_out_channels();
@@ -117,7 +128,7 @@ impl RenderContext {
         unsafe { from_raw_parts_mut(audio_out_ptr, n_frames * n_channels) }
     }
 
-    /// Access the audio input slice
+    /// Access the audio input slice, interleaved if `flags().is_interleaved()`
     pub fn audio_in(&self) -> &[f32] {
         let n_frames = self.audio_frames();
         let n_channels = self.audio_in_channels();
@@ -127,21 +138,21 @@ impl RenderContext {
 
     /// Access the digital input/output slice mutably
     pub fn digital_mut(&mut self) -> &mut [u32] {
+        // one word per frame, holding all channels
         let n_frames = self.digital_frames();
-        let n_channels = self.digital_channels();
         let digital_ptr = self.raw().digital;
-        unsafe { from_raw_parts_mut(digital_ptr, n_frames * n_channels) }
+        unsafe { from_raw_parts_mut(digital_ptr, n_frames) }
     }
 
     /// Access the digital input/output slice immutably
     pub fn digital(&self) -> &[u32] {
+        // one word per frame, holding all channels
         let n_frames = self.digital_frames();
-        let n_channels = self.digital_channels();
         let digital_ptr = self.raw().digital;
-        unsafe { from_raw_parts(digital_ptr, n_frames * n_channels) }
+        unsafe { from_raw_parts(digital_ptr, n_frames) }
     }
 
-    /// Access the analog output slice
+    /// Access the analog output slice, interleaved if `flags().is_interleaved()`
     pub fn analog_out(&mut self) -> &mut [f32] {
         let n_frames = self.analog_frames();
         let n_channels = self.analog_out_channels();
@@ -149,7 +160,7 @@ impl RenderContext {
         unsafe { from_raw_parts_mut(analog_out_ptr, n_frames * n_channels) }
     }
 
-    /// Access the analog input slice
+    /// Access the analog input slice, interleaved if `flags().is_interleaved()`
     pub fn analog_in(&self) -> &[f32] {
         let n_frames = self.analog_frames();
         let n_channels = self.analog_in_channels();
@@ -157,25 +168,32 @@ impl RenderContext {
         unsafe { from_raw_parts(analog_in_ptr, n_frames * n_channels) }
     }
 
+    /// Access the most recent value of each multiplexer input, if the
+    /// multiplexer is enabled
+    ///
+    /// The value of analog channel `c` at multiplexer channel `m` is
+    /// stored at index `m * analog_in_channels() + c`.
     pub fn multiplexer_analog_in(&self) -> Option<&[f32]> {
-        let n_frames = self.analog_frames();
-        let n_channels = self.multiplexer_channels();
+        let n_channels = self.analog_in_channels();
+        let n_mux_channels = self.multiplexer_channels();
         let analog_in_ptr = self.raw().multiplexerAnalogIn;
         if analog_in_ptr.is_null() {
             None
         } else {
-            Some(unsafe { from_raw_parts(analog_in_ptr, n_frames * n_channels) })
+            Some(unsafe { from_raw_parts(analog_in_ptr, n_mux_channels * n_channels) })
         }
     }
 
     /// Returns the value of a given digital input at the given frame number
     pub fn digital_read(&self, frame: usize, channel: usize) -> bool {
+        assert!(channel < 16, "digital channel {} out of range", channel);
         let digital = self.digital();
         (digital[frame] >> (channel + 16)) & 1 != 0
     }
 
     /// Sets a given digital output channel to a value for the current frame and all subsequent frames
     pub fn digital_write(&mut self, frame: usize, channel: usize, value: bool) {
+        assert!(channel < 16, "digital channel {} out of range", channel);
         let digital = self.digital_mut();
         for out in &mut digital[frame..] {
             if value {
@@ -188,6 +206,7 @@ impl RenderContext {
 
     /// Sets a given digital output channel to a value for the current frame only
     pub fn digital_write_once(&mut self, frame: usize, channel: usize, value: bool) {
+        assert!(channel < 16, "digital channel {} out of range", channel);
         let digital = self.digital_mut();
         if value {
             digital[frame] |= 1 << (channel + 16);
@@ -198,6 +217,7 @@ impl RenderContext {
 
     /// Sets the direction of a digital pin for the current frame and all subsequent frames
     pub fn pin_mode(&mut self, frame: usize, channel: usize, mode: DigitalDirection) {
+        assert!(channel < 16, "digital channel {} out of range", channel);
         let digital = self.digital_mut();
         for out in &mut digital[frame..] {
             match mode {
@@ -213,6 +233,7 @@ impl RenderContext {
 
     /// Sets the direction of a digital pin for the current frame only
     pub fn pin_mode_once(&mut self, frame: usize, channel: usize, mode: DigitalDirection) {
+        assert!(channel < 16, "digital channel {} out of range", channel);
         let digital = self.digital_mut();
         match mode {
             DigitalDirection::Input => {
@@ -0,0 +1,288 @@
+use std::slice::from_raw_parts_mut;
+
+use crate::{DigitalDirection, Error, RenderContext, SetupContext};
+
+/// Handle to a digital channel configured as input
+///
+/// Created via `SetupContext::digital_input`, which validates the channel
+/// and sets its direction once, so no bit shifts on raw channel indices
+/// are needed in `render`. Keeps the last value read, to detect edges
+/// across period boundaries.
+#[derive(Debug)]
+pub struct DigitalIn {
+    channel: usize,
+    last: bool,
+}
+
+/// Handle to a digital channel configured as output
+///
+/// Created via `SetupContext::digital_output`, which validates the channel
+/// and sets its direction once.
+#[derive(Debug)]
+pub struct DigitalOut {
+    channel: usize,
+}
+
+impl SetupContext {
+    /// Internal helper validating `channel` and setting its direction in
+    /// all frames, which persists into the first period
+    fn configure_digital(&mut self, channel: usize, mode: DigitalDirection) -> Result<(), Error> {
+        if channel >= self.digital_channels() {
+            return Err(Error::DigitalPin);
+        }
+        let digital = unsafe { from_raw_parts_mut(self.raw().digital, self.digital_frames()) };
+        for word in digital {
+            match mode {
+                DigitalDirection::Input => *word |= 1 << channel,
+                DigitalDirection::Output => *word &= !(1 << channel),
+            }
+        }
+        Ok(())
+    }
+
+    /// Configure digital `channel` as input and return a handle to it
+    ///
+    /// Returns `Error::DigitalPin` if the channel is not available.
+    pub fn digital_input(&mut self, channel: usize) -> Result<DigitalIn, Error> {
+        self.configure_digital(channel, DigitalDirection::Input)?;
+        Ok(DigitalIn {
+            channel,
+            last: false,
+        })
+    }
+
+    /// Configure digital `channel` as output and return a handle to it
+    ///
+    /// Returns `Error::DigitalPin` if the channel is not available.
+    pub fn digital_output(&mut self, channel: usize) -> Result<DigitalOut, Error> {
+        self.configure_digital(channel, DigitalDirection::Output)?;
+        Ok(DigitalOut { channel })
+    }
+}
+
+impl DigitalIn {
+    /// Channel index of this input
+    pub fn channel(&self) -> usize {
+        self.channel
+    }
+
+    /// Returns the value of the input at the given frame number
+    pub fn read(&self, context: &RenderContext, frame: usize) -> bool {
+        (context.digital()[frame] >> (self.channel + 16)) & 1 != 0
+    }
+
+    /// Iterate over all changes of the input within the current period,
+    /// yielding the frame and the new value
+    ///
+    /// Compares against the last value seen by the previous call, so
+    /// should be called once per period:
+    ///
+    /// ```
+    /// use bela::{RenderContext, TestRig};
+    ///
+    /// let mut rig = TestRig::new();
+    /// let mut button = rig.setup(|context| context.digital_input(3)).unwrap();
+    /// rig.script_digital_in(|frame, _channel| (4..40).contains(&frame));
+    /// let mut edges = Vec::new();
+    /// rig.render(2, |context: &mut RenderContext| {
+    ///     edges.extend(button.edges(context));
+    /// });
+    /// // 32 digital frames per period
+    /// assert_eq!(edges, [(4, true), (8, false)]);
+    /// ```
+    pub fn edges<'a>(&mut self, context: &'a RenderContext) -> Edges<'a> {
+        let digital = context.digital();
+        let previous = self.last;
+        if let Some(&word) = digital.last() {
+            self.last = (word >> (self.channel + 16)) & 1 != 0;
+        }
+        Edges {
+            digital: digital.iter().enumerate(),
+            bit: self.channel + 16,
+            previous,
+        }
+    }
+
+    /// Iterate over all frames within the current period at which the
+    /// input changes from low to high, see `DigitalIn::edges`
+    pub fn rising_edges<'a>(
+        &mut self,
+        context: &'a RenderContext,
+    ) -> impl Iterator<Item = usize> + 'a {
+        self.edges(context)
+            .filter(|&(_, value)| value)
+            .map(|(frame, _)| frame)
+    }
+
+    /// Iterate over all frames within the current period at which the
+    /// input changes from high to low, see `DigitalIn::edges`
+    pub fn falling_edges<'a>(
+        &mut self,
+        context: &'a RenderContext,
+    ) -> impl Iterator<Item = usize> + 'a {
+        self.edges(context)
+            .filter(|&(_, value)| !value)
+            .map(|(frame, _)| frame)
+    }
+}
+
+/// Iterator over the changes of a digital input, see `DigitalIn::edges`
+pub struct Edges<'a> {
+    digital: std::iter::Enumerate<std::slice::Iter<'a, u32>>,
+    bit: usize,
+    previous: bool,
+}
+
+impl Iterator for Edges<'_> {
+    type Item = (usize, bool);
+
+    fn next(&mut self) -> Option<(usize, bool)> {
+        for (frame, word) in &mut self.digital {
+            let value = (word >> self.bit) & 1 != 0;
+            if value != self.previous {
+                self.previous = value;
+                return Some((frame, value));
+            }
+        }
+        None
+    }
+}
+
+impl DigitalOut {
+    /// Channel index of this output
+    pub fn channel(&self) -> usize {
+        self.channel
+    }
+
+    /// Sets the output to a value for the given frame and all subsequent
+    /// frames
+    pub fn write(&self, context: &mut RenderContext, frame: usize, value: bool) {
+        context.digital_write(frame, self.channel, value);
+    }
+
+    /// Sets the output to a value for the given frame only
+    pub fn write_once(&self, context: &mut RenderContext, frame: usize, value: bool) {
+        context.digital_write_once(frame, self.channel, value);
+    }
+}
+
+/// A single frame of the digital buffer, holding all channels
+///
+/// Yielded by `RenderContext::digital_iter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigitalFrame(u32);
+
+impl DigitalFrame {
+    /// Values of all channels, one bit per channel
+    pub fn values(self) -> u16 {
+        (self.0 >> 16) as u16
+    }
+
+    /// Directions of all channels, with bits set for inputs
+    pub fn directions(self) -> u16 {
+        self.0 as u16
+    }
+
+    /// Values of all channels configured as inputs, with bits of outputs
+    /// cleared
+    pub fn inputs(self) -> u16 {
+        self.values() & self.directions()
+    }
+
+    /// Value of a single channel
+    pub fn read(self, channel: usize) -> bool {
+        assert!(channel < 16, "digital channel {} out of range", channel);
+        (self.values() >> channel) & 1 != 0
+    }
+
+    /// Direction of a single channel
+    pub fn direction(self, channel: usize) -> DigitalDirection {
+        assert!(channel < 16, "digital channel {} out of range", channel);
+        if (self.directions() >> channel) & 1 != 0 {
+            DigitalDirection::Input
+        } else {
+            DigitalDirection::Output
+        }
+    }
+
+    /// The raw digital word
+    pub fn raw(self) -> u32 {
+        self.0
+    }
+}
+
+/// Internal helper setting the value of `channel` in a digital word
+pub(crate) fn set_digital_value(word: &mut u32, channel: usize, value: bool) {
+    let bit = 1 << (channel + 16);
+    if value {
+        *word |= bit;
+    } else {
+        *word &= !bit;
+    }
+}
+
+/// Internal helper replacing the bits of `mask` in the value half of `word`
+fn apply_mask(word: u32, mask: u16, values: u16) -> u32 {
+    let mask = (mask as u32) << 16;
+    (word & !mask) | (((values as u32) << 16) & mask)
+}
+
+impl RenderContext {
+    /// Returns the values of all digital channels configured as inputs at
+    /// the given frame, one bit per channel, with bits of outputs cleared
+    ///
+    /// ```
+    /// use bela::{RenderContext, TestRig};
+    ///
+    /// let mut rig = TestRig::new();
+    /// rig.script_digital_in(|_frame, _channel| true);
+    /// rig.render(1, |context: &mut RenderContext| {
+    ///     // channels 0-7 become outputs, still holding the high input values
+    ///     context.set_directions(0xff00);
+    ///     assert_eq!(context.digital_inputs(0), 0xff00);
+    /// });
+    /// ```
+    pub fn digital_inputs(&self, frame: usize) -> u16 {
+        DigitalFrame(self.digital()[frame]).inputs()
+    }
+
+    /// Sets all digital outputs in `mask` to the corresponding bits of
+    /// `values` for the given frame and all subsequent frames
+    ///
+    /// ```
+    /// use bela::{RenderContext, TestRig};
+    ///
+    /// let mut rig = TestRig::new();
+    /// rig.render(1, |context: &mut RenderContext| {
+    ///     // channels 0-7 are outputs, 8-15 inputs
+    ///     context.set_directions(0xff00);
+    ///     context.write_digital_mask(0, 0x00ff, 0b1010);
+    /// });
+    /// assert!(rig.digital_out(0, 1) && !rig.digital_out(0, 2) && rig.digital_out(31, 3));
+    /// ```
+    pub fn write_digital_mask(&mut self, frame: usize, mask: u16, values: u16) {
+        for word in &mut self.digital_mut()[frame..] {
+            *word = apply_mask(*word, mask, values);
+        }
+    }
+
+    /// Sets all digital outputs in `mask` to the corresponding bits of
+    /// `values` for the given frame only
+    pub fn write_digital_mask_once(&mut self, frame: usize, mask: u16, values: u16) {
+        let word = &mut self.digital_mut()[frame];
+        *word = apply_mask(*word, mask, values);
+    }
+
+    /// Sets the directions of all digital channels for all frames, with
+    /// bits set in `inputs` for inputs and cleared for outputs
+    pub fn set_directions(&mut self, inputs: u16) {
+        for word in self.digital_mut() {
+            *word = (*word & 0xffff_0000) | inputs as u32;
+        }
+    }
+
+    /// Iterate over all frames of the digital buffer
+    pub fn digital_iter(&self) -> impl ExactSizeIterator<Item = DigitalFrame> + '_ {
+        self.digital().iter().map(|&word| DigitalFrame(word))
+    }
+}
@@ -0,0 +1,307 @@
+use crate::{Button, ButtonEvent, DigitalIn, RenderContext};
+
+/// Quarter step for each transition, indexed by `previous << 2 | current`
+/// where states are `a << 1 | b`. Transitions changing both channels at
+/// once are invalid and ignored.
+const TRANSITIONS: [i8; 16] = [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0];
+/// Smoothing factor of the velocity estimate, per period
+const VELOCITY_SMOOTHING: f32 = 0.2;
+
+/// Quadrature rotary encoder on two digital inputs
+///
+/// Decodes the A and B channels at every digital frame using a
+/// transition table, ignoring invalid transitions caused by bouncing
+/// contacts. Steps are counted in detents, i.e., clicks of the encoder,
+/// and are positive when A leads B. An optional push switch is handled
+/// as a `Button`.
+///
+/// ```
+/// use bela::{Encoder, TestRig};
+///
+/// let mut rig = TestRig::new();
+/// let (a, b) = rig.setup(|context| (context.digital_input(0), context.digital_input(1)));
+/// let mut encoder = Encoder::new(a.unwrap(), b.unwrap());
+///
+/// // one full quadrature cycle with A leading B, four frames per state
+/// let word = |a: u32, b: u32| (a | b << 1) << 16;
+/// let frames: Vec<u32> = [(0, 0), (1, 0), (1, 1), (0, 1), (0, 0)]
+///     .iter()
+///     .flat_map(|&(a, b)| std::iter::repeat(word(a, b)).take(4))
+///     .collect();
+/// assert_eq!(encoder.process_frames(&frames, 44100.), 1);
+/// // and back, with contacts bouncing on the way
+/// let frames: Vec<u32> = [(0, 0), (0, 1), (0, 0), (0, 1), (1, 1), (1, 0), (0, 0)]
+///     .iter()
+///     .map(|&(a, b)| word(a, b))
+///     .collect();
+/// assert_eq!(encoder.process_frames(&frames, 44100.), -1);
+/// assert_eq!(encoder.position(), 0);
+/// ```
+#[derive(Debug)]
+pub struct Encoder {
+    a: DigitalIn,
+    b: DigitalIn,
+    switch: Option<Button>,
+    steps_per_detent: i32,
+    active_low: bool,
+    /// Last state, `None` before the first frame was processed
+    state: Option<u8>,
+    /// Quarter steps not yet counted as detent
+    quarter_steps: i32,
+    position: i64,
+    delta: i32,
+    velocity: f32,
+}
+
+impl Encoder {
+    /// Create a new `Encoder` from its A and B channels, counting four
+    /// quarter steps (one full quadrature cycle) per detent
+    pub fn new(a: DigitalIn, b: DigitalIn) -> Self {
+        Self {
+            a,
+            b,
+            switch: None,
+            steps_per_detent: 4,
+            active_low: false,
+            state: None,
+            quarter_steps: 0,
+            position: 0,
+            delta: 0,
+            velocity: 0.,
+        }
+    }
+
+    /// Set the number of quarter steps per detent, typically 1, 2 or 4
+    pub fn steps_per_detent(mut self, steps: u8) -> Self {
+        self.steps_per_detent = steps.max(1) as _;
+        self
+    }
+
+    /// Set whether the channels are active low, which reverses the
+    /// resting state but not the direction
+    pub fn active_low(mut self, val: bool) -> Self {
+        self.active_low = val;
+        self
+    }
+
+    /// Add a push switch, processed together with the encoder
+    pub fn switch(mut self, switch: Button) -> Self {
+        self.switch = Some(switch);
+        self
+    }
+
+    /// Accumulated position in detents
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    /// Reset the accumulated position
+    pub fn reset_position(&mut self) {
+        self.position = 0;
+        self.quarter_steps = 0;
+    }
+
+    /// Detents moved during the last processed period
+    pub fn delta(&self) -> i32 {
+        self.delta
+    }
+
+    /// Smoothed rotation speed in detents per second, positive when A
+    /// leads B
+    ///
+    /// ```
+    /// use bela::{Encoder, TestRig};
+    ///
+    /// let mut rig = TestRig::new();
+    /// let (a, b) = rig.setup(|context| (context.digital_input(0), context.digital_input(1)));
+    /// let mut encoder = Encoder::new(a.unwrap(), b.unwrap()).steps_per_detent(1);
+    ///
+    /// // one quarter step every 8 frames at 1 kHz, i.e., 125 steps per second
+    /// let states = [0b00, 0b01, 0b11, 0b10];
+    /// let frames: Vec<u32> = (0..32).map(|frame| states[(frame / 8) % 4] << 16).collect();
+    /// for _ in 0..100 {
+    ///     encoder.process_frames(&frames, 1000.);
+    /// }
+    /// assert!((encoder.velocity() - 125.).abs() < 1.);
+    /// ```
+    pub fn velocity(&self) -> f32 {
+        self.velocity
+    }
+
+    /// Check if the push switch is currently pressed
+    pub fn is_pressed(&self) -> bool {
+        self.switch.as_ref().map_or(false, Button::is_pressed)
+    }
+
+    /// Events of the push switch during the last processed period
+    pub fn switch_events(&self) -> &[(usize, ButtonEvent)] {
+        self.switch.as_ref().map_or(&[], Button::events)
+    }
+
+    /// Process the digital frames of the current period, returning the
+    /// number of detents moved
+    pub fn process(&mut self, context: &RenderContext) -> i32 {
+        self.process_frames(context.digital(), context.digital_sample_rate())
+    }
+
+    /// Process raw digital words as found in `RenderContext::digital`,
+    /// see `Encoder::process`
+    pub fn process_frames(&mut self, frames: &[u32], sample_rate: f32) -> i32 {
+        let (bit_a, bit_b) = (self.a.channel() + 16, self.b.channel() + 16);
+        let invert = if self.active_low { 0b11 } else { 0b00 };
+
+        let position = self.position;
+        for word in frames {
+            let state = ((((word >> bit_a) & 1) << 1 | ((word >> bit_b) & 1)) as u8) ^ invert;
+            let previous = self.state.replace(state).unwrap_or(state);
+            self.quarter_steps += TRANSITIONS[(previous << 2 | state) as usize] as i32;
+            let detents = self.quarter_steps / self.steps_per_detent;
+            self.position += detents as i64;
+            self.quarter_steps -= detents * self.steps_per_detent;
+        }
+        self.delta = (self.position - position) as i32;
+
+        if !frames.is_empty() {
+            let instant = self.delta as f32 * sample_rate / frames.len() as f32;
+            self.velocity += VELOCITY_SMOOTHING * (instant - self.velocity);
+        }
+        if let Some(switch) = &mut self.switch {
+            switch.process_frames(frames, sample_rate);
+        }
+
+        self.delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rig::{digital_inputs, digital_words};
+
+    /// Channels of A and B
+    const AB: &[usize] = &[0, 1];
+    /// One quadrature cycle with A leading B, as levels of A and B
+    const FORWARD: [&str; 4] = ["00", "10", "11", "01"];
+
+    /// Encoder on channels 0 (A) and 1 (B), with a push switch on
+    /// channel 2
+    fn encoder() -> Encoder {
+        let [a, b, switch] = digital_inputs([0, 1, 2]);
+        Encoder::new(a, b).switch(Button::new(switch).debounce_ms(0.))
+    }
+
+    /// Cycle through `FORWARD` or backwards, ending at rest
+    fn cycles(count: usize, forward: bool) -> Vec<u32> {
+        let mut states: Vec<&str> = FORWARD
+            .iter()
+            .copied()
+            .cycle()
+            .take(count * 4 + 1)
+            .collect();
+        if !forward {
+            states.reverse();
+        }
+        digital_words(AB, &states.concat())
+    }
+
+    #[test]
+    fn counts_detents() {
+        for &(steps, detents) in &[(1, 8), (2, 4), (4, 2)] {
+            let mut encoder = encoder().steps_per_detent(steps);
+            assert_eq!(encoder.process_frames(&cycles(2, true), 1000.), detents);
+            assert_eq!(
+                encoder.process_frames(&cycles(1, false), 1000.),
+                -detents / 2
+            );
+            assert_eq!(encoder.position(), detents as i64 / 2);
+            assert_eq!(encoder.delta(), -detents / 2);
+        }
+    }
+
+    #[test]
+    fn active_low_keeps_direction() {
+        let mut encoder = encoder().active_low(true);
+        let inverted: Vec<u32> = cycles(1, true)
+            .iter()
+            .map(|word| word ^ (0b11 << 16))
+            .collect();
+        assert_eq!(encoder.process_frames(&inverted, 1000.), 1);
+        let inverted: Vec<u32> = cycles(1, false)
+            .iter()
+            .map(|word| word ^ (0b11 << 16))
+            .collect();
+        assert_eq!(encoder.process_frames(&inverted, 1000.), -1);
+    }
+
+    #[test]
+    fn ignores_invalid_transitions() {
+        let mut encoder = encoder().steps_per_detent(1);
+        // both channels changing at once, also across periods
+        let bouncing = digital_words(AB, "00 11 00 11");
+        assert_eq!(encoder.process_frames(&bouncing, 1000.), 0);
+        assert_eq!(encoder.process_frames(&bouncing[1..], 1000.), 0);
+        // decoding continues from the last state
+        assert_eq!(
+            encoder.process_frames(&digital_words(AB, "10 00"), 1000.),
+            -2
+        );
+    }
+
+    #[test]
+    fn carries_partial_detents() {
+        let mut encoder = encoder();
+        assert_eq!(
+            encoder.process_frames(&digital_words(AB, "00 10 11"), 1000.),
+            0
+        );
+        assert_eq!(
+            encoder.process_frames(&digital_words(AB, "01 00"), 1000.),
+            1
+        );
+        assert_eq!(encoder.position(), 1);
+
+        // resetting discards partial detents
+        assert_eq!(
+            encoder.process_frames(&digital_words(AB, "10 11"), 1000.),
+            0
+        );
+        encoder.reset_position();
+        assert_eq!(encoder.position(), 0);
+        assert_eq!(
+            encoder.process_frames(&digital_words(AB, "01 00"), 1000.),
+            0
+        );
+        assert_eq!(encoder.position(), 0);
+    }
+
+    #[test]
+    fn empty_frames() {
+        let mut encoder = encoder().steps_per_detent(1);
+        assert_eq!(encoder.process_frames(&[], 1000.), 0);
+        assert_eq!(encoder.velocity(), 0.);
+
+        // 4 quarter steps in 5 frames at 1 kHz are 800 steps per second
+        assert_eq!(encoder.process_frames(&cycles(1, true), 1000.), 4);
+        let velocity = encoder.velocity();
+        assert_eq!(velocity, VELOCITY_SMOOTHING * 800.);
+        assert_eq!(encoder.process_frames(&[], 1000.), 0);
+        assert_eq!(encoder.delta(), 0);
+        assert_eq!(encoder.velocity(), velocity);
+    }
+
+    #[test]
+    fn passes_switch_events() {
+        let mut encoder = encoder();
+        assert!(encoder.switch_events().is_empty());
+        let mut words = cycles(1, true);
+        words[2..].iter_mut().for_each(|word| *word |= 1 << 18);
+        assert_eq!(encoder.process_frames(&words, 1000.), 1);
+        assert_eq!(encoder.switch_events(), [(2, ButtonEvent::Pressed)]);
+        assert!(encoder.is_pressed());
+
+        let [a, b] = digital_inputs([0, 1]);
+        let mut without_switch = Encoder::new(a, b);
+        without_switch.process_frames(&words, 1000.);
+        assert!(without_switch.switch_events().is_empty() && !without_switch.is_pressed());
+    }
+}
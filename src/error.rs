@@ -1,12 +1,21 @@
-#[derive(Copy, Clone, Debug)]
+use crate::ConfigError;
+
+#[derive(Clone, Debug)]
 #[non_exhaustive]
 pub enum Error {
+    Config(ConfigError),
+    Hardware,
     Init,
     Start,
     CreateTask,
     ScheduleTask,
+    Record,
+    Codec,
+    DigitalPin,
     #[cfg(feature = "midi")]
     Midi,
+    #[cfg(feature = "offline")]
+    Wav,
 }
 
 impl std::fmt::Display for Error {
@@ -18,12 +27,32 @@ impl std::fmt::Display for Error {
 impl std::error::Error for Error {
     fn description(&self) -> &str {
         match self {
+            Error::Config(_) => "invalid settings",
+            Error::Hardware => "Bela_detectHw or Bela_HwConfig_new error",
             Error::Init => "Bela_initAudio error",
-            Error::Start => "Bela_startAudio error",
+            Error::Start => "Bela_startAudio error or application already running",
             Error::CreateTask => "Bela_createAuxiliaryTask error",
             Error::ScheduleTask => "Bela_scheduleAuxiliaryTask error",
+            Error::Record => "session log error",
+            Error::Codec => "codec control error",
+            Error::DigitalPin => "invalid digital pin",
             #[cfg(feature = "midi")]
             Error::Midi => "Midi_new error",
+            #[cfg(feature = "offline")]
+            Error::Wav => "WAV file error",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Config(err) => Some(err),
+            _ => None,
         }
     }
 }
+
+impl From<ConfigError> for Error {
+    fn from(err: ConfigError) -> Self {
+        Error::Config(err)
+    }
+}
@@ -0,0 +1,93 @@
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+/// Flags of a Bela context, as returned by `Context::flags`
+///
+/// Wraps the `BELA_FLAG_*` bits of the C API:
+///
+/// ```
+/// use bela::BelaFlags;
+///
+/// let flags = BelaFlags::INTERLEAVED | BelaFlags::DETECT_UNDERRUNS;
+/// assert!(flags.is_interleaved());
+/// assert!(!flags.analog_outputs_persist());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BelaFlags(u32);
+
+impl BelaFlags {
+    /// Audio and analog samples are interleaved
+    pub const INTERLEAVED: Self = Self(bela_sys::BELA_FLAG_INTERLEAVED);
+    /// Analog outputs keep their last value in subsequent periods
+    pub const ANALOG_OUTPUTS_PERSIST: Self = Self(bela_sys::BELA_FLAG_ANALOG_OUTPUTS_PERSIST);
+    /// Underruns are detected and logged
+    pub const DETECT_UNDERRUNS: Self = Self(bela_sys::BELA_FLAG_DETECT_UNDERRUNS);
+    /// The context is rendered offline rather than in real time
+    pub const OFFLINE: Self = Self(bela_sys::BELA_FLAG_OFFLINE);
+
+    /// No flags set
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Create flags from raw bits, keeping unknown bits
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Raw bits of the flags
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Check if all flags in `other` are set
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Check if no flags are set
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Check if audio and analog samples are interleaved
+    pub const fn is_interleaved(self) -> bool {
+        self.contains(Self::INTERLEAVED)
+    }
+
+    /// Check if analog outputs persist
+    pub const fn analog_outputs_persist(self) -> bool {
+        self.contains(Self::ANALOG_OUTPUTS_PERSIST)
+    }
+
+    /// Check if underruns are detected
+    pub const fn detect_underruns(self) -> bool {
+        self.contains(Self::DETECT_UNDERRUNS)
+    }
+
+    /// Check if the context is rendered offline
+    pub const fn is_offline(self) -> bool {
+        self.contains(Self::OFFLINE)
+    }
+}
+
+impl BitOr for BelaFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for BelaFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for BelaFlags {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
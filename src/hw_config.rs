@@ -0,0 +1,124 @@
+use std::panic::UnwindSafe;
+
+use crate::platform::{Current, Platform};
+use crate::{Bela, BelaApplication, BelaHw, Error, SetupContext};
+
+/// Audio codec found on a board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// TI TLV320AIC3104, the stereo codec of Bela, BelaMini and Salt
+    Tlv320Aic3104,
+    /// Analog Devices AD1938, the codec of the CTAG cape
+    Ad1938,
+}
+
+/// Hardware configuration of a board
+///
+/// Available before running the application via `HwConfig::detect`,
+/// `HwConfig::for_board` or `Bela::hw_config`, e.g., to choose settings
+/// depending on the connected board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HwConfig {
+    board: BelaHw,
+    audio_sample_rate: f32,
+    audio_in_channels: usize,
+    audio_out_channels: usize,
+    analog_in_channels: usize,
+    analog_out_channels: usize,
+    digital_channels: usize,
+}
+
+impl HwConfig {
+    /// Detect the connected board and query its configuration
+    pub fn detect() -> Result<Self, Error> {
+        let board = BelaHw::from_raw(Current::detect_hw()).ok_or(Error::Hardware)?;
+        Self::for_board(board)
+    }
+
+    /// Query the configuration of the given board
+    pub fn for_board(board: BelaHw) -> Result<Self, Error> {
+        let config = Current::hw_config(board as _).ok_or(Error::Hardware)?;
+        Ok(Self {
+            board,
+            audio_sample_rate: config.audioSampleRate,
+            audio_in_channels: config.audioInChannels as _,
+            audio_out_channels: config.audioOutChannels as _,
+            analog_in_channels: config.analogInChannels as _,
+            analog_out_channels: config.analogOutChannels as _,
+            digital_channels: config.digitalChannels as _,
+        })
+    }
+
+    /// The board this configuration describes
+    pub fn board(&self) -> BelaHw {
+        self.board
+    }
+
+    /// Audio codecs of the board, with one entry per codec chip
+    pub fn codecs(&self) -> &'static [Codec] {
+        match self.board {
+            BelaHw::NoHw => &[],
+            BelaHw::Bela | BelaHw::BelaMini | BelaHw::Salt => &[Codec::Tlv320Aic3104],
+            BelaHw::CtagFace => &[Codec::Ad1938],
+            BelaHw::CtagBeast => &[Codec::Ad1938, Codec::Ad1938],
+            BelaHw::CtagFaceBela => &[Codec::Tlv320Aic3104, Codec::Ad1938],
+            BelaHw::CtagBeastBela => &[Codec::Tlv320Aic3104, Codec::Ad1938, Codec::Ad1938],
+        }
+    }
+
+    /// Audio sample rate used on this board
+    pub fn audio_sample_rate(&self) -> f32 {
+        self.audio_sample_rate
+    }
+
+    /// Audio sample rates supported on this board
+    ///
+    /// Bela runs each board at a single, fixed audio sample rate, which
+    /// is currently the only entry.
+    pub fn supported_sample_rates(&self) -> &[f32] {
+        std::slice::from_ref(&self.audio_sample_rate)
+    }
+
+    /// Maximum number of audio input channels
+    pub fn audio_in_channels(&self) -> usize {
+        self.audio_in_channels
+    }
+
+    /// Maximum number of audio output channels
+    pub fn audio_out_channels(&self) -> usize {
+        self.audio_out_channels
+    }
+
+    /// Maximum number of analog input channels
+    pub fn analog_in_channels(&self) -> usize {
+        self.analog_in_channels
+    }
+
+    /// Maximum number of analog output channels
+    pub fn analog_out_channels(&self) -> usize {
+        self.analog_out_channels
+    }
+
+    /// Maximum number of digital channels
+    pub fn digital_channels(&self) -> usize {
+        self.digital_channels
+    }
+}
+
+impl<Application, Constructor> Bela<Constructor>
+where
+    Application: BelaApplication,
+    Constructor: Send + UnwindSafe + FnOnce(&mut SetupContext) -> Option<Application>,
+{
+    /// Query the hardware configuration the application will run on
+    ///
+    /// Uses the board selected via `Bela::board`, or detects the
+    /// connected board otherwise.
+    pub fn hw_config(&self) -> Result<HwConfig, Error> {
+        match BelaHw::from_raw(self.settings.board as _) {
+            Some(BelaHw::NoHw) => HwConfig::detect(),
+            Some(board) => HwConfig::for_board(board),
+            None => Err(Error::Hardware),
+        }
+    }
+}
@@ -1,24 +1,26 @@
 use std::ops::{Deref, DerefMut};
 
+use crate::platform::{Current, Platform};
+
 /// Internal wrapper for `bela_sys::BelaInitSettings` implementing
 /// `Deref` and `DerefMut` with `Target = bela_sys::BelaInitSettings`.
-#[cfg(feature = "static")]
+#[cfg(any(feature = "static", feature = "simulation"))]
 pub(crate) struct InitSettings(bela_sys::BelaInitSettings);
 
 /// Internal wrapper for `bela_sys::BelaInitSettings` implementing
 /// `Deref` and `DerefMut` with `Target = bela_sys::BelaInitSettings`.
-#[cfg(not(feature = "static"))]
+#[cfg(not(any(feature = "static", feature = "simulation")))]
 pub(crate) struct InitSettings(*mut bela_sys::BelaInitSettings);
 
 impl Default for InitSettings {
     fn default() -> InitSettings {
-        // When linking against the static Bela library, InitSettings
-        // contains a bela_sys::BelaInitSettings directly, initialized
-        // using bela_sys::Bela_defaultSettings.
-        #[cfg(feature = "static")]
+        // When linking against the static Bela library or running the
+        // simulation, InitSettings contains a bela_sys::BelaInitSettings
+        // directly, initialized using the platform's default settings.
+        #[cfg(any(feature = "static", feature = "simulation"))]
         let settings = unsafe {
             let mut settings = std::mem::MaybeUninit::<bela_sys::BelaInitSettings>::uninit();
-            bela_sys::Bela_defaultSettings(settings.as_mut_ptr());
+            Current::default_settings(settings.as_mut_ptr());
             settings.assume_init()
         };
 
@@ -26,11 +28,11 @@ impl Default for InitSettings {
         // contains a pointer to bela_sys::BelaInitSettings, allocated
         // via bela_sys::Bela_InitSettings_alloc and initialized using
         // bela_sys::Bela_defaultSettings for ABI compatibility.
-        #[cfg(not(feature = "static"))]
+        #[cfg(not(any(feature = "static", feature = "simulation")))]
         let settings = unsafe {
             let settings = bela_sys::Bela_InitSettings_alloc();
             assert!(!settings.is_null());
-            bela_sys::Bela_defaultSettings(settings);
+            Current::default_settings(settings);
             settings
         };
 
@@ -38,7 +40,7 @@ impl Default for InitSettings {
     }
 }
 
-#[cfg(not(feature = "static"))]
+#[cfg(not(any(feature = "static", feature = "simulation")))]
 impl Drop for InitSettings {
     fn drop(&mut self) {
         // When linking against the dynamic Bela library, the settings
@@ -52,24 +54,24 @@ impl Drop for InitSettings {
 
 impl Deref for InitSettings {
     type Target = bela_sys::BelaInitSettings;
-    #[cfg(feature = "static")]
+    #[cfg(any(feature = "static", feature = "simulation"))]
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 
-    #[cfg(not(feature = "static"))]
+    #[cfg(not(any(feature = "static", feature = "simulation")))]
     fn deref(&self) -> &Self::Target {
         unsafe { &*self.0 }
     }
 }
 
 impl DerefMut for InitSettings {
-    #[cfg(feature = "static")]
+    #[cfg(any(feature = "static", feature = "simulation"))]
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 
-    #[cfg(not(feature = "static"))]
+    #[cfg(not(any(feature = "static", feature = "simulation")))]
     fn deref_mut(&mut self) -> &mut Self::Target {
         unsafe { &mut *self.0 }
     }
@@ -0,0 +1,124 @@
+use std::slice::{from_raw_parts, from_raw_parts_mut};
+
+use crate::{Buffer, BufferMut, RenderContext};
+
+/// Simultaneous borrows of all buffers of a `RenderContext`
+///
+/// Created via `RenderContext::io`. Allows processing inputs directly into
+/// outputs without copying them to a scratch buffer first:
+///
+/// ```
+/// use bela::{RenderContext, TestRig};
+///
+/// let mut rig = TestRig::new();
+/// rig.script_audio_in(|frame, _channel| frame as f32);
+/// rig.render(1, |context: &mut RenderContext| {
+///     let io = context.io();
+///     for (out, sample) in io.audio_out.iter_mut().zip(io.audio_in) {
+///         *out = 0.5 * sample;
+///     }
+/// });
+/// assert_eq!(rig.audio_out()[2], 0.5);
+/// ```
+pub struct Io<'a> {
+    /// Audio input samples
+    pub audio_in: &'a [f32],
+    /// Audio output samples
+    pub audio_out: &'a mut [f32],
+    /// Analog input samples
+    pub analog_in: &'a [f32],
+    /// Analog output samples
+    pub analog_out: &'a mut [f32],
+    /// Digital words, one per frame, holding inputs, outputs and directions
+    pub digital: &'a mut [u32],
+    /// Multiplexer analog inputs, if the multiplexer is enabled, see
+    /// `RenderContext::multiplexer_analog_in`
+    pub mux_in: Option<&'a [f32]>,
+    audio_frames: usize,
+    audio_in_channels: usize,
+    audio_out_channels: usize,
+    analog_frames: usize,
+    analog_in_channels: usize,
+    analog_out_channels: usize,
+    interleaved: bool,
+}
+
+impl Io<'_> {
+    /// View of the audio input buffer
+    pub fn audio_in_view(&self) -> Buffer<'_> {
+        Buffer::new(
+            self.audio_in,
+            self.audio_frames,
+            self.audio_in_channels,
+            self.interleaved,
+        )
+    }
+
+    /// View of the audio output buffer
+    pub fn audio_out_view(&mut self) -> BufferMut<'_> {
+        BufferMut::new(
+            self.audio_out,
+            self.audio_frames,
+            self.audio_out_channels,
+            self.interleaved,
+        )
+    }
+
+    /// View of the analog input buffer
+    pub fn analog_in_view(&self) -> Buffer<'_> {
+        Buffer::new(
+            self.analog_in,
+            self.analog_frames,
+            self.analog_in_channels,
+            self.interleaved,
+        )
+    }
+
+    /// View of the analog output buffer
+    pub fn analog_out_view(&mut self) -> BufferMut<'_> {
+        BufferMut::new(
+            self.analog_out,
+            self.analog_frames,
+            self.analog_out_channels,
+            self.interleaved,
+        )
+    }
+}
+
+impl RenderContext {
+    /// Borrow all buffers at once
+    pub fn io(&mut self) -> Io<'_> {
+        let audio_frames = self.audio_frames();
+        let audio_in_channels = self.audio_in_channels();
+        let audio_out_channels = self.audio_out_channels();
+        let analog_frames = self.analog_frames();
+        let analog_in_channels = self.analog_in_channels();
+        let analog_out_channels = self.analog_out_channels();
+        let digital_frames = self.digital_frames();
+        let interleaved = self.flags().is_interleaved();
+        let mux_in = self
+            .multiplexer_analog_in()
+            .map(|mux_in| (mux_in.as_ptr(), mux_in.len()));
+        let raw = self.raw();
+
+        // all buffers are distinct allocations owned by the Bela core, and
+        // the returned borrows are tied to the mutable borrow of `self`
+        unsafe {
+            Io {
+                audio_in: from_raw_parts(raw.audioIn, audio_frames * audio_in_channels),
+                audio_out: from_raw_parts_mut(raw.audioOut, audio_frames * audio_out_channels),
+                analog_in: from_raw_parts(raw.analogIn, analog_frames * analog_in_channels),
+                analog_out: from_raw_parts_mut(raw.analogOut, analog_frames * analog_out_channels),
+                digital: from_raw_parts_mut(raw.digital, digital_frames),
+                mux_in: mux_in.map(|(ptr, len)| from_raw_parts(ptr, len)),
+                audio_frames,
+                audio_in_channels,
+                audio_out_channels,
+                analog_frames,
+                analog_in_channels,
+                analog_out_channels,
+                interleaved,
+            }
+        }
+    }
+}
@@ -14,17 +14,14 @@
 //! // contains safe code, as it must also be realtime safe
 //! unsafe impl BelaApplication for Example {
 //!     fn render(&mut self, context: &mut RenderContext) {
-//!         let audio_out_channels = context.audio_out_channels();
-//!         for frame in context.audio_out().chunks_exact_mut(audio_out_channels) {
+//!         for mut frame in context.audio_out_view().frames_mut() {
 //!             let gain = 0.5;
 //!             let signal = 2. * (self.0 as f32 * 110. / 44100.) - 1.;
 //!             self.0 += 1;
 //!             if self.0 as f32 > 44100. / 110. {
 //!                 self.0 = 0;
 //!             }
-//!             for sample in frame {
-//!                 *sample = gain * signal;
-//!             }
+//!             frame.fill(gain * signal);
 //!         }
 //!     }
 //! }
@@ -33,31 +30,107 @@
 //!     Bela::new(|_context| Some(Example(0))).run()
 //! }
 //! ```
+//!
+//! `Bela::run` blocks until the application stops. To keep the calling
+//! thread available, e.g., for a user interface or network server,
+//! `Bela::start` returns a `RunningBela` handle instead.
+//!
+//! `Bela::from_args` creates the builder from the standard Bela
+//! command-line options (`--period`, `--use-analog`, `--dac-level`, ...),
+//! so Rust programs can be configured like any other Bela project.
+//!
+//! When the `simulation` feature is enabled, the Bela C library is not
+//! called at all. Instead, a pure Rust driver runs `setup`, `render` and
+//! `cleanup` on a timer thread at the rate implied by the settings, with
+//! silent inputs. This allows running Bela applications on development
+//! machines and in CI.
 
 use nix::sys::signal;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::ffi::c_void;
 use std::ops::DerefMut;
 use std::os::raw::c_int;
-use std::panic::{catch_unwind, UnwindSafe};
+use std::panic::{catch_unwind, AssertUnwindSafe, UnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 mod init_settings;
 use crate::init_settings::*;
 
+mod platform;
+use crate::platform::{Current, Platform};
+
+mod owned_context;
+
 mod context;
 pub use crate::context::*;
 
+mod flags;
+pub use crate::flags::*;
+
+mod buffer;
+pub use crate::buffer::*;
+
+mod io;
+pub use crate::io::*;
+
+mod digital;
+pub use crate::digital::*;
+
+mod button;
+pub use crate::button::*;
+
+mod encoder;
+pub use crate::encoder::*;
+
+mod pwm;
+pub use crate::pwm::*;
+
+mod matrix;
+pub use crate::matrix::*;
+
+mod serial;
+pub use crate::serial::*;
+
+mod mux;
+pub use crate::mux::*;
+
 mod bela_hw;
 pub use crate::bela_hw::*;
 
 mod error;
 pub use crate::error::*;
 
+mod config;
+pub use crate::config::{ConfigError, ConfigIssue};
+
+mod args;
+pub use crate::args::*;
+
+mod settings;
+pub use crate::settings::*;
+
+mod hw_config;
+pub use crate::hw_config::*;
+
 mod auxiliary_task;
 pub use crate::auxiliary_task::*;
 
+mod codec;
+pub use crate::codec::*;
+
+mod test_rig;
+pub use crate::test_rig::*;
+
+mod record;
+pub use crate::record::*;
+
+#[cfg(feature = "offline")]
+mod offline;
+#[cfg(feature = "offline")]
+pub use crate::offline::*;
+
 #[cfg(feature = "midi")]
 mod midi;
 #[cfg(feature = "midi")]
@@ -99,6 +172,14 @@ pub use crate::midi::*;
 /// exist in C).
 pub unsafe trait BelaApplication: Sized + Send {
     fn render(&mut self, context: &mut RenderContext);
+
+    /// Called once after the audio thread has stopped, before the
+    /// application object is returned or dropped
+    ///
+    /// Unlike `render`, `cleanup` is not real-time critical. It can be
+    /// used to, e.g., set analog outputs to a safe value or flush logs.
+    /// Does nothing by default.
+    fn cleanup(&mut self, _context: &mut CleanupContext) {}
 }
 
 /// The main entry point for Bela applications
@@ -112,8 +193,16 @@ pub struct Bela<Constructor> {
     settings: InitSettings,
     /// `BelaApplication` constructor, set on creation.
     constructor: Constructor,
+    /// Problems found by setters, reported by `Bela::build`
+    issues: Vec<ConfigIssue>,
 }
 
+/// A `Bela` builder with validated settings
+///
+/// Created via `Bela::build`, which checks all settings at once instead
+/// of failing on the first invalid one.
+pub struct ValidatedBela<Constructor>(Bela<Constructor>);
+
 /// Internal user data definition
 ///
 /// `UserData` can either be empty (`None`), contain a `BelaApplication`
@@ -152,14 +241,27 @@ where
         Self {
             settings: InitSettings::default(),
             constructor,
+            issues: Vec::new(),
+        }
+    }
+
+    /// Internal helper converting a setting to its C type, recording an
+    /// issue if it does not fit
+    fn convert<T: TryFrom<usize>>(&mut self, setting: &'static str, value: usize) -> Option<T> {
+        let converted = value.try_into().ok();
+        if converted.is_none() {
+            self.issues.push(ConfigIssue::Overflow { setting, value });
         }
+        converted
     }
 
     /// Set number of analog frames per period (buffer). Number of audio frames
     /// depends on relative sample rates of the two. By default, audio is twice
     /// the sample rate, so has twice the period size.
     pub fn period_size(mut self, size: usize) -> Self {
-        self.settings.periodSize = size.try_into().unwrap();
+        if let Some(size) = self.convert("period_size", size) {
+            self.settings.periodSize = size;
+        }
         self
     }
 
@@ -177,19 +279,25 @@ where
 
     /// Set number of requested analog input channels
     pub fn num_analog_in_channels(mut self, num: usize) -> Self {
-        self.settings.numAnalogInChannels = num.try_into().unwrap();
+        if let Some(num) = self.convert("num_analog_in_channels", num) {
+            self.settings.numAnalogInChannels = num;
+        }
         self
     }
 
     /// Set number of requested analog output channels
     pub fn num_analog_out_channels(mut self, num: usize) -> Self {
-        self.settings.numAnalogOutChannels = num.try_into().unwrap();
+        if let Some(num) = self.convert("num_analog_out_channels", num) {
+            self.settings.numAnalogOutChannels = num;
+        }
         self
     }
 
     /// Set number of requested digital channels
     pub fn num_digital_channels(mut self, num: usize) -> Self {
-        self.settings.numDigitalChannels = num.try_into().unwrap();
+        if let Some(num) = self.convert("num_digital_channels", num) {
+            self.settings.numDigitalChannels = num;
+        }
         self
     }
 
@@ -201,48 +309,50 @@ where
 
     /// Set initial audio DAC level
     pub fn dac_level(mut self, val: f32) -> Self {
-        assert!(val.is_finite());
         self.settings.dacLevel = val;
         self
     }
 
     /// Set initial audio ADC level
     pub fn adc_level(mut self, val: f32) -> Self {
-        assert!(val.is_finite());
         self.settings.adcLevel = val;
         self
     }
 
     /// Set initial gain for left and right PGA channels
     pub fn pga_gain(mut self, val: [f32; 2]) -> Self {
-        assert!(val[0].is_finite());
-        assert!(val[1].is_finite());
         self.settings.pgaGain = val;
         self
     }
 
     /// Set initial headphone level
     pub fn headphone_level(mut self, val: f32) -> Self {
-        assert!(val.is_finite());
         self.settings.headphoneLevel = val;
         self
     }
 
-    /// Set the number of requested multiplexer channels
+    /// Set the number of multiplexer channels per analog input, which
+    /// requires a board supporting the multiplexer capelet
     pub fn num_mux_channels(mut self, val: usize) -> Self {
-        self.settings.numMuxChannels = val.try_into().unwrap();
+        if let Some(val) = self.convert("num_mux_channels", val) {
+            self.settings.numMuxChannels = val;
+        }
         self
     }
 
     /// Set the number of requested audio expander inputs
     pub fn audio_expander_inputs(mut self, val: usize) -> Self {
-        self.settings.audioExpanderInputs = val.try_into().unwrap();
+        if let Some(val) = self.convert("audio_expander_inputs", val) {
+            self.settings.audioExpanderInputs = val;
+        }
         self
     }
 
     /// Set the number of requested audio expander outputs
     pub fn audio_expander_outputs(mut self, val: usize) -> Self {
-        self.settings.audioExpanderOutputs = val.try_into().unwrap();
+        if let Some(val) = self.convert("audio_expander_outputs", val) {
+            self.settings.audioExpanderOutputs = val;
+        }
         self
     }
 
@@ -251,7 +361,6 @@ where
     /// # Safety
     /// TODO: unclear if this should be considered safe or not
     pub unsafe fn pru_number(mut self, val: i32) -> Self {
-        assert!(val == 0 || val == 1);
         self.settings.pruNumber = val;
         self
     }
@@ -285,13 +394,7 @@ where
 
     /// Set or disable the stop button pin (0-127)
     pub fn stop_button_pin(mut self, val: Option<i8>) -> Self {
-        self.settings.stopButtonPin = match val {
-            Some(v) => {
-                assert!(v >= 0);
-                v as _
-            }
-            None => -1,
-        };
+        self.settings.stopButtonPin = val.map_or(-1, |v| v as _);
         self
     }
 
@@ -322,25 +425,23 @@ where
 
     /// Set the requested audio thread stack size
     pub fn audio_thread_stack_size(mut self, num: usize) -> Self {
-        self.settings.audioThreadStackSize = num.try_into().unwrap();
+        if let Some(num) = self.convert("audio_thread_stack_size", num) {
+            self.settings.audioThreadStackSize = num;
+        }
         self
     }
 
     /// Set the requested stack size for all auxiliary task threads
     pub fn auxiliary_task_stack_size(mut self, num: usize) -> Self {
-        self.settings.auxiliaryTaskStackSize = num.try_into().unwrap();
+        if let Some(num) = self.convert("auxiliary_task_stack_size", num) {
+            self.settings.auxiliaryTaskStackSize = num;
+        }
         self
     }
 
     /// Set or disable the amplifier mute button pin (0-127)
     pub fn amp_mute_pin(mut self, val: Option<i8>) -> Self {
-        self.settings.ampMutePin = match val {
-            Some(v) => {
-                assert!(v >= 0);
-                v as _
-            }
-            None => -1,
-        };
+        self.settings.ampMutePin = val.map_or(-1, |v| v as _);
         self
     }
 
@@ -350,106 +451,260 @@ where
         self
     }
 
-    /// Consumes the `Bela` object and runs the application
+    /// Validate all settings
     ///
-    /// Terminates on error, or as soon as the application stops
-    pub fn run(self) -> Result<(), Error> {
+    /// Checks value ranges, the period size, and channel counts against
+    /// the limits of the selected board, or the detected board if none is
+    /// selected, returning every problem found.
+    /// `Bela::run` and `Bela::start` perform the same validation, returning
+    /// the `ConfigError` wrapped in `Error::Config` on failure.
+    pub fn build(self) -> Result<ValidatedBela<Constructor>, ConfigError> {
         let Self {
-            mut settings,
+            settings,
             constructor,
+            issues,
         } = self;
+        config::validate(&settings, issues)?;
+        Ok(ValidatedBela(Self {
+            settings,
+            constructor,
+            issues: Vec::new(),
+        }))
+    }
 
-        let mut user_data: UserData<Application, _> = UserData::Constructor(constructor);
+    /// Consumes the `Bela` object and runs the application
+    ///
+    /// Terminates on error, or as soon as the application stops
+    pub fn run(self) -> Result<(), Error> {
+        self.start()?.wait().map(drop)
+    }
 
-        /// C-compatible trampoline function to call constructor
-        extern "C" fn setup_trampoline<Application, Constructor>(
-            context: *mut bela_sys::BelaContext,
-            user_data: *mut c_void,
-        ) -> bool
-        where
-            Application: BelaApplication,
-            Constructor: Send + UnwindSafe + FnOnce(&mut SetupContext) -> Option<Application>,
-        {
-            // create application instance
-            // constructor is consumed
-            let user_data = unsafe { &mut *(user_data as *mut UserData<Application, Constructor>) };
-            let constructor = user_data.take();
-            if let UserData::Constructor(constructor) = constructor {
-                *user_data = match catch_unwind(|| {
-                    let mut context = unsafe { Context::<SetupTag>::new(context) };
-                    constructor(&mut context)
-                }) {
-                    Ok(application) => application.map_or(UserData::None, UserData::Application),
-                    Err(_) => UserData::None,
-                };
-            }
-            user_data.is_application()
-        }
+    /// Consumes the `Bela` object and runs the application, returning the
+    /// application object once the Bela audio thread has been stopped and
+    /// cleaned up
+    ///
+    /// This allows persisting state accumulated during the run, e.g.,
+    /// statistics or recorded buffers, before exiting.
+    pub fn run_and_return(self) -> Result<Application, Error> {
+        self.start()?.wait()
+    }
 
-        /// C-compatible trampoline function to call our render function
-        extern "C" fn render_trampoline<Application, Constructor>(
-            context: *mut bela_sys::BelaContext,
-            user_data: *mut c_void,
-        ) where
-            Application: BelaApplication,
-        {
-            let user_data = unsafe { &mut *(user_data as *mut UserData<Application, Constructor>) };
-            if let UserData::Application(user_data) = user_data {
-                // NOTE: cannot use catch_unwind safely here, as it returns a boxed error (-> allocation in RT thread)
-                let mut context = unsafe { Context::<RenderTag>::new(context) };
-                user_data.render(&mut context);
-            };
-        }
+    /// Consumes the `Bela` object and starts the application without
+    /// blocking
+    ///
+    /// Returns a `RunningBela` handle that can be used to stop the
+    /// application and wait for it to terminate. As the Bela API is
+    /// global, only a single application may run at a time, and
+    /// `Error::Start` is returned while another one is running.
+    pub fn start(self) -> Result<RunningBela<Application, Constructor>, Error> {
+        self.build()?.start()
+    }
+}
+
+impl<Application, Constructor> ValidatedBela<Constructor>
+where
+    Application: BelaApplication,
+    Constructor: Send + UnwindSafe + FnOnce(&mut SetupContext) -> Option<Application>,
+{
+    /// Consumes the `ValidatedBela` object and runs the application, see
+    /// `Bela::run`
+    pub fn run(self) -> Result<(), Error> {
+        self.start()?.wait().map(drop)
+    }
+
+    /// Consumes the `ValidatedBela` object and runs the application,
+    /// returning the application object, see `Bela::run_and_return`
+    pub fn run_and_return(self) -> Result<Application, Error> {
+        self.start()?.wait()
+    }
 
-        /// C-compatible trampoline function to consume (and thereby drop)
-        /// our application object
-        extern "C" fn cleanup_trampoline<Application, Constructor>(
-            _context: *mut bela_sys::BelaContext,
-            user_data: *mut c_void,
-        ) {
-            let _ = catch_unwind(|| {
-                // drop application instance
-                let user_data =
-                    unsafe { &mut *(user_data as *mut UserData<Application, Constructor>) };
-                user_data.take();
-            });
+    /// Consumes the `ValidatedBela` object and starts the application
+    /// without blocking, see `Bela::start`
+    pub fn start(self) -> Result<RunningBela<Application, Constructor>, Error> {
+        // released by `RunningBela` once cleaned up
+        if RUNNING.swap(true, Ordering::SeqCst) {
+            return Err(Error::Start);
         }
 
+        let Bela {
+            mut settings,
+            constructor,
+            ..
+        } = self.0;
+
+        let user_data = Box::into_raw(Box::new(UserData::<Application, _>::Constructor(
+            constructor,
+        )));
+
         // set up our trampoline functions as setup/render/cleanup
         settings.setup = Some(setup_trampoline::<Application, Constructor>);
         settings.render = Some(render_trampoline::<Application, Constructor>);
         settings.cleanup = Some(cleanup_trampoline::<Application, Constructor>);
 
+        let mut running = RunningBela {
+            settings,
+            user_data,
+            stopped: true,
+        };
+
         if unsafe {
-            bela_sys::Bela_initAudio(
-                settings.deref_mut() as *mut _,
-                &mut user_data as *mut _ as *mut _,
-            )
+            Current::init_audio(running.settings.deref_mut() as *mut _, user_data as *mut _)
         } != 0
         {
             return Err(Error::Init);
         }
 
-        if unsafe { bela_sys::Bela_startAudio() } != 0 {
-            unsafe {
-                bela_sys::Bela_stopAudio();
-                bela_sys::Bela_cleanupAudio();
-            }
+        running.stopped = false;
+        if Current::start_audio() != 0 {
+            running.stop();
             return Err(Error::Start);
         }
 
         setup_signal_handler();
 
-        while unsafe { bela_sys::Bela_stopRequested() == 0 } {
+        Ok(running)
+    }
+}
+
+/// Set while a `RunningBela` exists, as the Bela API only supports a
+/// single application at a time
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// C-compatible trampoline function to call constructor
+extern "C" fn setup_trampoline<Application, Constructor>(
+    context: *mut bela_sys::BelaContext,
+    user_data: *mut c_void,
+) -> bool
+where
+    Application: BelaApplication,
+    Constructor: Send + UnwindSafe + FnOnce(&mut SetupContext) -> Option<Application>,
+{
+    // create application instance
+    // constructor is consumed
+    let user_data = unsafe { &mut *(user_data as *mut UserData<Application, Constructor>) };
+    let constructor = user_data.take();
+    if let UserData::Constructor(constructor) = constructor {
+        *user_data = match catch_unwind(|| {
+            let mut context = unsafe { Context::<SetupTag>::new(context) };
+            constructor(&mut context)
+        }) {
+            Ok(application) => application.map_or(UserData::None, UserData::Application),
+            Err(_) => UserData::None,
+        };
+    }
+    user_data.is_application()
+}
+
+/// C-compatible trampoline function to call our render function
+extern "C" fn render_trampoline<Application, Constructor>(
+    context: *mut bela_sys::BelaContext,
+    user_data: *mut c_void,
+) where
+    Application: BelaApplication,
+{
+    let user_data = unsafe { &mut *(user_data as *mut UserData<Application, Constructor>) };
+    if let UserData::Application(user_data) = user_data {
+        // NOTE: cannot use catch_unwind safely here, as it returns a boxed error (-> allocation in RT thread)
+        let mut context = unsafe { Context::<RenderTag>::new(context) };
+        user_data.render(&mut context);
+    };
+}
+
+/// C-compatible trampoline function to call our cleanup function
+///
+/// The application object is kept, so it can be returned by
+/// `RunningBela::wait`.
+extern "C" fn cleanup_trampoline<Application, Constructor>(
+    context: *mut bela_sys::BelaContext,
+    user_data: *mut c_void,
+) where
+    Application: BelaApplication,
+{
+    let user_data = unsafe { &mut *(user_data as *mut UserData<Application, Constructor>) };
+    if let UserData::Application(application) = user_data {
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            let mut context = unsafe { Context::<CleanupTag>::new(context) };
+            application.cleanup(&mut context);
+        }));
+    }
+}
+
+/// Handle to a running Bela application
+///
+/// Returned by `Bela::start`. Dropping the handle stops the application
+/// and waits for it to terminate, then drops the application object.
+pub struct RunningBela<Application, Constructor> {
+    /// Settings passed to the Bela API, kept alive while running
+    settings: InitSettings,
+    /// `UserData` passed to the Bela API, owned by the handle
+    user_data: *mut UserData<Application, Constructor>,
+    /// Set once the audio thread has been stopped and cleaned up
+    stopped: bool,
+}
+
+impl<Application, Constructor> RunningBela<Application, Constructor> {
+    /// Request the application to stop, without waiting for it to
+    /// terminate
+    pub fn request_stop(&self) {
+        Current::request_stop();
+    }
+
+    /// Check if the application is still running, i.e., no stop has been
+    /// requested via `request_stop`, a signal, or the stop button
+    pub fn is_running(&self) -> bool {
+        !self.stopped && !Current::stop_requested()
+    }
+
+    /// Wait until a stop is requested, then stop the application and
+    /// return the application object
+    pub fn wait(mut self) -> Result<Application, Error> {
+        while self.is_running() {
             sleep(Duration::new(0, 100000));
         }
+        self.stop();
+        // the application is only missing if setup failed, in which case
+        // `Bela::start` would already have returned an error
+        match unsafe { &mut *self.user_data }.take() {
+            UserData::Application(application) => Ok(application),
+            _ => Err(Error::Init),
+        }
+    }
 
-        unsafe {
-            bela_sys::Bela_stopAudio();
-            bela_sys::Bela_cleanupAudio();
+    /// Wait until a stop is requested or `timeout` has passed
+    ///
+    /// Returns `true` if the application is no longer running. The
+    /// application still needs to be stopped using `wait` or by dropping
+    /// the handle.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.is_running() {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            sleep((deadline - now).min(Duration::new(0, 100000)));
+        }
+        true
+    }
+
+    /// Internal function to stop the audio thread and clean up
+    fn stop(&mut self) {
+        if !self.stopped {
+            Current::stop_audio();
+            Current::cleanup_audio();
+            self.stopped = true;
         }
+    }
+}
 
-        Ok(())
+impl<Application, Constructor> Drop for RunningBela<Application, Constructor> {
+    fn drop(&mut self) {
+        if !self.stopped {
+            Current::request_stop();
+        }
+        self.stop();
+        drop(unsafe { Box::from_raw(self.user_data) });
+        RUNNING.store(false, Ordering::SeqCst);
     }
 }
 
@@ -457,9 +712,7 @@ where
 /// Bela audio thread on `SIGINT` and `SIGTERM`
 fn setup_signal_handler() {
     extern "C" fn signal_handler(_signal: c_int) {
-        unsafe {
-            bela_sys::Bela_requestStop();
-        }
+        Current::request_stop();
     }
 
     let handler = signal::SigHandler::Handler(signal_handler);
@@ -472,3 +725,30 @@ fn setup_signal_handler() {
         signal::sigaction(signal::SIGTERM, &sig_action).unwrap();
     }
 }
+
+#[cfg(all(test, feature = "simulation"))]
+mod tests {
+    use super::*;
+
+    struct Silence;
+
+    unsafe impl BelaApplication for Silence {
+        fn render(&mut self, _context: &mut RenderContext) {}
+    }
+
+    #[test]
+    fn runs_single_application() {
+        let running = Bela::new(|_| Some(Silence)).start().unwrap();
+        assert!(matches!(
+            Bela::new(|_| Some(Silence)).start(),
+            Err(Error::Start)
+        ));
+        assert!(running.is_running());
+
+        running.request_stop();
+        running.wait().unwrap();
+        // released once cleaned up, also when dropping the handle
+        drop(Bela::new(|_| Some(Silence)).start().unwrap());
+        assert!(Bela::new(|_| Some(Silence)).start().is_ok());
+    }
+}
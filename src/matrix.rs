@@ -0,0 +1,529 @@
+use crate::digital::set_digital_value;
+use crate::{Error, RenderContext, SetupContext};
+
+/// Maximum number of rows and columns
+const MAX_LINES: usize = 8;
+/// Maximum number of events reported per call to `MatrixScanner::process`
+const MAX_EVENTS: usize = 16;
+
+/// Event reported by a `MatrixScanner`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEvent {
+    /// The key at the given row and column was pressed
+    Pressed { row: usize, column: usize },
+    /// The key at the given row and column was released
+    Released { row: usize, column: usize },
+}
+
+/// Scanner for button and LED matrices of up to 8x8 on the digital
+/// channels
+///
+/// Created via `SetupContext::matrix_scanner`. Rows are outputs, driven
+/// active one after another. Each row is held for a slot of frames: the
+/// columns first drive the LEDs of the row as outputs, if enabled, then
+/// turn into inputs, and after the settle time the keys of the row are
+/// read. Keys are debounced, and key presses which could be caused by
+/// ghosting in matrices without diodes are held back until unambiguous.
+///
+/// Digital outputs written in one period are only visible on the inputs
+/// two periods later, so inputs are matched with the row driven
+/// `latency_frames` earlier, by default two periods.
+///
+/// ```
+/// use bela::{KeyEvent, MatrixScanner, TestRig};
+///
+/// let mut rig = TestRig::new();
+/// let scanner = rig.setup(|context| context.matrix_scanner(&[0, 1], &[2, 3]));
+/// let mut scanner: MatrixScanner = scanner.unwrap().debounce_ms(0.);
+///
+/// // key at row 1, column 0 closed: column 2 follows row 1 while it is an input
+/// let respond = |word: u32| {
+///     let (row_1, column_in) = (word & (1 << 17) != 0, word & (1 << 2) != 0);
+///     word & !(0b1100 << 16) | (((row_1 && column_in) as u32) << 18)
+/// };
+/// let mut periods = vec![[0u32; 32]; 2];
+/// let mut events = Vec::new();
+/// for _ in 0..4 {
+///     let mut frames = periods[periods.len() - 2].map(respond);
+///     events.extend(scanner.process_frames(&mut frames, 44100.).iter().map(|&(_, e)| e));
+///     periods.push(frames);
+/// }
+/// assert_eq!(events, [KeyEvent::Pressed { row: 1, column: 0 }]);
+/// assert!(scanner.is_pressed(1, 0) && !scanner.is_pressed(0, 0));
+/// ```
+#[derive(Debug)]
+pub struct MatrixScanner {
+    rows: [usize; MAX_LINES],
+    num_rows: usize,
+    columns: [usize; MAX_LINES],
+    num_columns: usize,
+    active_low: bool,
+    diodes: bool,
+    settle_frames: usize,
+    led_frames: usize,
+    latency_frames: Option<usize>,
+    debounce_ms: f32,
+    /// Frames scanned since creation
+    elapsed: u64,
+    /// Last raw key states, one bit per column
+    raw: [u8; MAX_LINES],
+    /// Debounced key states, one bit per column
+    keys: [u8; MAX_LINES],
+    /// Number of consecutive scans differing from the debounced state
+    bouncing: [[u16; MAX_LINES]; MAX_LINES],
+    leds: [u8; MAX_LINES],
+    events: [(usize, KeyEvent); MAX_EVENTS],
+    num_events: usize,
+}
+
+/// Internal helper setting the direction of `channel` in a digital word
+fn set_input(word: &mut u32, channel: usize, input: bool) {
+    if input {
+        *word |= 1 << channel;
+    } else {
+        *word &= !(1 << channel);
+    }
+}
+
+impl SetupContext {
+    /// Create a `MatrixScanner` driving `rows` and reading `columns`
+    ///
+    /// Returns `Error::DigitalPin` if there are no rows or more than 8
+    /// rows or columns, or if a channel is not available or used twice.
+    pub fn matrix_scanner(
+        &mut self,
+        rows: &[usize],
+        columns: &[usize],
+    ) -> Result<MatrixScanner, Error> {
+        if rows.is_empty() || rows.len() > MAX_LINES || columns.len() > MAX_LINES {
+            return Err(Error::DigitalPin);
+        }
+        let mut used = 0u16;
+        for &channel in rows.iter().chain(columns) {
+            if channel >= self.digital_channels() || used & (1 << channel) != 0 {
+                return Err(Error::DigitalPin);
+            }
+            used |= 1 << channel;
+        }
+        for &row in rows {
+            self.digital_output(row)?;
+        }
+        for &column in columns {
+            self.digital_input(column)?;
+        }
+
+        let mut scanner = MatrixScanner {
+            rows: [0; MAX_LINES],
+            num_rows: rows.len(),
+            columns: [0; MAX_LINES],
+            num_columns: columns.len(),
+            active_low: false,
+            diodes: false,
+            settle_frames: 2,
+            led_frames: 0,
+            latency_frames: None,
+            debounce_ms: 5.,
+            elapsed: 0,
+            raw: [0; MAX_LINES],
+            keys: [0; MAX_LINES],
+            bouncing: [[0; MAX_LINES]; MAX_LINES],
+            leds: [0; MAX_LINES],
+            events: [(0, KeyEvent::Pressed { row: 0, column: 0 }); MAX_EVENTS],
+            num_events: 0,
+        };
+        scanner.rows[..rows.len()].copy_from_slice(rows);
+        scanner.columns[..columns.len()].copy_from_slice(columns);
+        Ok(scanner)
+    }
+}
+
+impl MatrixScanner {
+    /// Set whether rows are active and keys read as pressed while low,
+    /// e.g., when using pull-up resistors
+    pub fn active_low(mut self, val: bool) -> Self {
+        self.active_low = val;
+        self
+    }
+
+    /// Set whether the keys have diodes, which rules out ghosting
+    pub fn diodes(mut self, val: bool) -> Self {
+        self.diodes = val;
+        self
+    }
+
+    /// Set the number of frames to wait after switching the columns to
+    /// inputs before reading them, 2 by default
+    pub fn settle_frames(mut self, frames: usize) -> Self {
+        self.settle_frames = frames;
+        self
+    }
+
+    /// Set the number of frames per row the columns drive the LEDs, 0 by
+    /// default, which disables the LEDs
+    pub fn led_frames(mut self, frames: usize) -> Self {
+        self.led_frames = frames;
+        self
+    }
+
+    /// Set the delay between writing outputs and reading the response on
+    /// the inputs, two periods by default
+    pub fn latency_frames(mut self, frames: usize) -> Self {
+        self.latency_frames = Some(frames);
+        self
+    }
+
+    /// Set the time a key has to be stable before a change is reported,
+    /// 5 ms by default
+    pub fn debounce_ms(mut self, ms: f32) -> Self {
+        self.debounce_ms = ms;
+        self
+    }
+
+    /// Number of rows
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    /// Number of columns
+    pub fn num_columns(&self) -> usize {
+        self.num_columns
+    }
+
+    /// Check if the key at `row` and `column` is pressed (debounced)
+    pub fn is_pressed(&self, row: usize, column: usize) -> bool {
+        assert!(row < self.num_rows, "row {} out of range", row);
+        assert!(column < self.num_columns, "column {} out of range", column);
+        (self.keys[row] >> column) & 1 != 0
+    }
+
+    /// Debounced key states, one bit per column for each row
+    pub fn keys(&self) -> &[u8] {
+        &self.keys[..self.num_rows]
+    }
+
+    /// Turn the LED at `row` and `column` on or off
+    pub fn set_led(&mut self, row: usize, column: usize, on: bool) {
+        assert!(row < self.num_rows, "row {} out of range", row);
+        assert!(column < self.num_columns, "column {} out of range", column);
+        if on {
+            self.leds[row] |= 1 << column;
+        } else {
+            self.leds[row] &= !(1 << column);
+        }
+    }
+
+    /// LED frame buffer, one bit per column for each row
+    pub fn leds_mut(&mut self) -> &mut [u8] {
+        &mut self.leds[..self.num_rows]
+    }
+
+    /// Events reported by the last call to `MatrixScanner::process`
+    pub fn events(&self) -> &[(usize, KeyEvent)] {
+        &self.events[..self.num_events]
+    }
+
+    /// Scan the digital frames of the current period, returning all key
+    /// events with the digital frame at which they were detected
+    ///
+    /// At most 16 events are reported per period, further events are
+    /// dropped.
+    pub fn process(&mut self, context: &mut RenderContext) -> &[(usize, KeyEvent)] {
+        let sample_rate = context.digital_sample_rate();
+        self.process_frames(context.digital_mut(), sample_rate)
+    }
+
+    /// Scan raw digital words as found in `RenderContext::digital_mut`,
+    /// see `MatrixScanner::process`
+    ///
+    /// Directions and values are set on the words directly instead of via
+    /// `RenderContext::pin_mode_once`, `digital_write_once` and
+    /// `digital_read`, so scanning works without a context and does not
+    /// repeat the channel checks done on creation for every frame.
+    pub fn process_frames(&mut self, frames: &mut [u32], sample_rate: f32) -> &[(usize, KeyEvent)] {
+        let slot = (self.led_frames + self.settle_frames + 1) as u64;
+        let scan = slot * self.num_rows as u64;
+        let latency = self.latency_frames.unwrap_or(2 * frames.len()) as u64;
+        let debounce_frames = (self.debounce_ms * sample_rate / 1000.).max(0.) as u64;
+        let debounce = ((debounce_frames + scan - 1) / scan).max(1) as u16;
+
+        self.num_events = 0;
+        for (frame, word) in frames.iter_mut().enumerate() {
+            // read the response to the outputs written `latency` frames ago
+            if let Some(past) = self.elapsed.checked_sub(latency) {
+                if past % slot == slot - 1 {
+                    let row = ((past / slot) % self.num_rows as u64) as usize;
+                    self.read_row(frame, row, *word, debounce);
+                }
+            }
+
+            let row = ((self.elapsed / slot) % self.num_rows as u64) as usize;
+            let drive_leds = self.elapsed % slot < self.led_frames as u64;
+            for (index, &channel) in self.rows[..self.num_rows].iter().enumerate() {
+                set_input(word, channel, false);
+                set_digital_value(word, channel, (index == row) != self.active_low);
+            }
+            for (index, &channel) in self.columns[..self.num_columns].iter().enumerate() {
+                set_input(word, channel, !drive_leds);
+                let lit = drive_leds && (self.leds[row] >> index) & 1 != 0;
+                set_digital_value(word, channel, lit != self.active_low);
+            }
+            self.elapsed += 1;
+        }
+
+        &self.events[..self.num_events]
+    }
+
+    fn read_row(&mut self, frame: usize, row: usize, word: u32, debounce: u16) {
+        let mut raw = 0;
+        for (index, &channel) in self.columns[..self.num_columns].iter().enumerate() {
+            let value = (word >> (channel + 16)) & 1 != 0;
+            raw |= ((value != self.active_low) as u8) << index;
+        }
+        self.raw[row] = raw;
+
+        for column in 0..self.num_columns {
+            let pressed = (raw >> column) & 1 != 0;
+            if pressed == self.is_pressed(row, column)
+                || (pressed && !self.diodes && self.is_ghost(row, column))
+            {
+                self.bouncing[row][column] = 0;
+                continue;
+            }
+            self.bouncing[row][column] += 1;
+            if self.bouncing[row][column] >= debounce {
+                self.bouncing[row][column] = 0;
+                self.keys[row] ^= 1 << column;
+                self.push(
+                    frame,
+                    if pressed {
+                        KeyEvent::Pressed { row, column }
+                    } else {
+                        KeyEvent::Released { row, column }
+                    },
+                );
+            }
+        }
+    }
+
+    /// Check if the key could be a ghost, i.e., if it forms a rectangle
+    /// with three other pressed keys
+    fn is_ghost(&self, row: usize, column: usize) -> bool {
+        let others = self.raw[row] & !(1 << column);
+        (0..self.num_rows).any(|other| {
+            other != row && (self.raw[other] >> column) & 1 != 0 && self.raw[other] & others != 0
+        })
+    }
+
+    fn push(&mut self, frame: usize, event: KeyEvent) {
+        if let Some(slot) = self.events.get_mut(self.num_events) {
+            *slot = (frame, event);
+            self.num_events += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestRig;
+
+    /// Digital frames per period
+    const FRAMES: usize = 32;
+
+    /// Simulated key matrix responding to the outputs of a scanner
+    struct Matrix {
+        rows: Vec<usize>,
+        columns: Vec<usize>,
+        keys: Vec<Vec<bool>>,
+        diodes: bool,
+        active_low: bool,
+        /// Outputs of the past periods, read back with two periods latency
+        outputs: Vec<Vec<u32>>,
+    }
+
+    impl Matrix {
+        fn new(rows: &[usize], columns: &[usize]) -> Self {
+            Self {
+                rows: rows.to_vec(),
+                columns: columns.to_vec(),
+                keys: vec![vec![false; columns.len()]; rows.len()],
+                diodes: false,
+                active_low: false,
+                outputs: vec![vec![0; FRAMES]; 2],
+            }
+        }
+
+        fn scanner(&self) -> MatrixScanner {
+            let mut rig = TestRig::new();
+            rig.setup(|context| context.matrix_scanner(&self.rows, &self.columns))
+                .unwrap()
+                .debounce_ms(0.)
+                .active_low(self.active_low)
+        }
+
+        /// Columns connected to the active row through pressed keys,
+        /// including ghost keys if there are no diodes
+        fn connected(&self, active: Option<usize>) -> Vec<bool> {
+            let mut rows = vec![false; self.rows.len()];
+            let mut columns = vec![false; self.columns.len()];
+            if let Some(active) = active {
+                rows[active] = true;
+            }
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for (row, keys) in self.keys.iter().enumerate() {
+                    for (column, &pressed) in keys.iter().enumerate() {
+                        if pressed && rows[row] && !columns[column] {
+                            columns[column] = true;
+                            changed = true;
+                        }
+                        if pressed && !self.diodes && columns[column] && !rows[row] {
+                            rows[row] = true;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+            columns
+        }
+
+        fn respond(&self, mut word: u32) -> u32 {
+            let level = |channel: usize| (word >> (channel + 16)) & 1 != 0;
+            let active = self
+                .rows
+                .iter()
+                .position(|&row| word & (1 << row) == 0 && level(row) != self.active_low);
+            for (&channel, connected) in self.columns.iter().zip(self.connected(active)) {
+                if word & (1 << channel) != 0 {
+                    word &= !(1 << (channel + 16));
+                    word |= ((connected != self.active_low) as u32) << (channel + 16);
+                }
+            }
+            word
+        }
+
+        /// Run the scanner for a number of periods, returning all events
+        fn scan(&mut self, scanner: &mut MatrixScanner, periods: usize) -> Vec<KeyEvent> {
+            let mut events = Vec::new();
+            for _ in 0..periods {
+                let past = &self.outputs[self.outputs.len() - 2];
+                let mut frames: Vec<u32> = past.iter().map(|&word| self.respond(word)).collect();
+                let found = scanner.process_frames(&mut frames, 44100.);
+                events.extend(found.iter().map(|&(_, event)| event));
+                self.outputs.push(frames);
+            }
+            events
+        }
+    }
+
+    fn pressed(row: usize, column: usize) -> KeyEvent {
+        KeyEvent::Pressed { row, column }
+    }
+
+    #[test]
+    fn reports_presses_and_releases() {
+        let mut matrix = Matrix::new(&[0, 1, 2], &[3, 4]);
+        let mut scanner = matrix.scanner();
+        matrix.keys[2][1] = true;
+        assert_eq!(matrix.scan(&mut scanner, 4), [pressed(2, 1)]);
+        assert_eq!(scanner.keys(), [0, 0, 0b10]);
+        matrix.keys[2][1] = false;
+        assert_eq!(
+            matrix.scan(&mut scanner, 4),
+            [KeyEvent::Released { row: 2, column: 1 }]
+        );
+    }
+
+    #[test]
+    fn active_low() {
+        let mut matrix = Matrix::new(&[0, 1], &[2, 3]);
+        matrix.active_low = true;
+        let mut scanner = matrix.scanner();
+        matrix.keys[1][0] = true;
+        assert_eq!(matrix.scan(&mut scanner, 4), [pressed(1, 0)]);
+        assert!(scanner.is_pressed(1, 0) && !scanner.is_pressed(0, 0));
+    }
+
+    #[test]
+    fn holds_back_ghost_keys() {
+        let mut matrix = Matrix::new(&[0, 1], &[2, 3]);
+        let mut scanner = matrix.scanner();
+        // (1, 1) reads as pressed through the three other keys
+        matrix.keys[0] = vec![true, true];
+        matrix.keys[1][0] = true;
+        assert_eq!(matrix.scan(&mut scanner, 4), [pressed(0, 0), pressed(0, 1)]);
+        assert!(!scanner.is_pressed(1, 0) && !scanner.is_pressed(1, 1));
+
+        // once unambiguous, the real key is reported
+        matrix.keys[0][1] = false;
+        let events = matrix.scan(&mut scanner, 4);
+        assert!(events.contains(&pressed(1, 0)));
+        assert!(!scanner.is_pressed(1, 1));
+    }
+
+    #[test]
+    fn diodes_rule_out_ghosting() {
+        let mut matrix = Matrix::new(&[0, 1], &[2, 3]);
+        matrix.diodes = true;
+        matrix.keys = vec![vec![true, true], vec![true, true]];
+        let mut scanner = matrix.scanner();
+        let mut with_diodes = matrix.scanner().diodes(true);
+        assert_eq!(matrix.scan(&mut scanner, 4).len(), 2);
+        matrix.outputs.truncate(2);
+        assert_eq!(matrix.scan(&mut with_diodes, 4).len(), 4);
+        assert_eq!(with_diodes.keys(), [0b11, 0b11]);
+    }
+
+    #[test]
+    fn drives_leds() {
+        let mut rig = TestRig::new();
+        let scanner = rig.setup(|context| context.matrix_scanner(&[0, 1], &[2, 3, 4]));
+        let mut scanner = scanner.unwrap().led_frames(2);
+        scanner.set_led(1, 2, true);
+        assert_eq!(scanner.leds_mut(), [0, 0b100]);
+
+        // slots of 5 frames: 2 LED frames, 2 settle frames and 1 read frame
+        let mut frames = [0u32; 10];
+        scanner.process_frames(&mut frames, 44100.);
+        let column = |word: u32, channel: usize| {
+            let input = word & (1 << channel) != 0;
+            (input, (word >> (channel + 16)) & 1 != 0)
+        };
+        for (frame, &word) in frames.iter().enumerate() {
+            let row_1 = frame >= 5;
+            let leds = frame % 5 < 2;
+            assert_eq!(column(word, 0), (false, !row_1), "row 0 at frame {}", frame);
+            assert_eq!(column(word, 1), (false, row_1), "row 1 at frame {}", frame);
+            assert_eq!(column(word, 2), (!leds, false));
+            assert_eq!(column(word, 4), (!leds, row_1 && leds));
+        }
+    }
+
+    #[test]
+    fn limits_events_per_period() {
+        let channels: Vec<usize> = (0..16).collect();
+        let mut matrix = Matrix::new(&channels[..8], &channels[8..]);
+        matrix.diodes = true;
+        matrix.keys = vec![vec![true; 8]; 8];
+        let mut scanner = matrix.scanner().diodes(true);
+        assert_eq!(matrix.scan(&mut scanner, 2), []);
+        // each period reads about 10 rows of 8 keys
+        assert_eq!(matrix.scan(&mut scanner, 1).len(), MAX_EVENTS);
+        assert_eq!(scanner.events().len(), MAX_EVENTS);
+        matrix.scan(&mut scanner, 2);
+        assert_eq!(scanner.keys(), [0xff; 8]);
+    }
+
+    #[test]
+    #[should_panic(expected = "row 2 out of range")]
+    fn checks_led_row() {
+        Matrix::new(&[0, 1], &[2, 3]).scanner().set_led(2, 0, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "column 2 out of range")]
+    fn checks_key_column() {
+        Matrix::new(&[0, 1], &[2, 3]).scanner().is_pressed(0, 2);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::RenderContext;
+
+/// View of the multiplexer capelet inputs
+///
+/// Created via `RenderContext::mux_inputs`. The capelet switches all
+/// analog inputs to the next multiplexer channel every analog frame, and
+/// the Bela core keeps the most recent value of each combination of
+/// analog channel and multiplexer channel. With fewer analog frames per
+/// period than multiplexer channels, only some values are updated in each
+/// period.
+///
+/// ```
+/// use bela::{RenderContext, TestRig};
+///
+/// let mut rig = TestRig::new().period_size(2).num_mux_channels(8);
+/// rig.script_multiplexer_analog_in(|_frame, channel, mux| (10 * channel + mux) as f32);
+/// rig.render(1, |context: &mut RenderContext| {
+///     let mux = context.mux_inputs().unwrap();
+///     // one multiplexer channel per analog frame, 2 frames per period
+///     assert_eq!(mux.updated().collect::<Vec<_>>(), [0, 1]);
+///     assert_eq!(mux.read(3, 1), 31.);
+///     assert!(!mux.is_updated(2) && mux.read(3, 2) == 0.);
+/// });
+/// rig.render(1, |context: &mut RenderContext| {
+///     let mux = context.mux_inputs().unwrap();
+///     assert_eq!(mux.updated().collect::<Vec<_>>(), [2, 3]);
+///     assert_eq!(mux.read(3, 2), 32.);
+/// });
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct MuxInputs<'a> {
+    values: &'a [f32],
+    analog_channels: usize,
+    mux_channels: usize,
+    starting_channel: usize,
+    analog_frames: usize,
+}
+
+impl RenderContext {
+    /// View of the multiplexer inputs, if the multiplexer is enabled
+    pub fn mux_inputs(&self) -> Option<MuxInputs<'_>> {
+        let values = self.multiplexer_analog_in()?;
+        Some(MuxInputs {
+            values,
+            analog_channels: self.analog_in_channels(),
+            mux_channels: self.multiplexer_channels(),
+            starting_channel: self.multiplexer_starting_channel(),
+            analog_frames: self.analog_frames(),
+        })
+    }
+}
+
+impl<'a> MuxInputs<'a> {
+    /// Number of analog channels
+    pub fn num_analog_channels(&self) -> usize {
+        self.analog_channels
+    }
+
+    /// Number of multiplexer channels per analog channel
+    pub fn num_mux_channels(&self) -> usize {
+        self.mux_channels
+    }
+
+    /// Total number of inputs
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Check if there are no inputs
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Most recent value of the input at the given analog channel and
+    /// multiplexer channel
+    pub fn read(&self, analog_channel: usize, mux_channel: usize) -> f32 {
+        assert!(
+            analog_channel < self.analog_channels,
+            "analog channel {} out of range",
+            analog_channel
+        );
+        assert!(
+            mux_channel < self.mux_channels,
+            "multiplexer channel {} out of range",
+            mux_channel
+        );
+        self.values[mux_channel * self.analog_channels + analog_channel]
+    }
+
+    /// Multiplexer channel sampled at the given analog frame
+    pub fn mux_channel_for_frame(&self, frame: usize) -> usize {
+        (self.starting_channel + frame) % self.mux_channels
+    }
+
+    /// Check if the inputs of a multiplexer channel were sampled during
+    /// the current period
+    pub fn is_updated(&self, mux_channel: usize) -> bool {
+        let offset = (mux_channel + self.mux_channels - self.starting_channel) % self.mux_channels;
+        offset < self.analog_frames
+    }
+
+    /// Iterate over the multiplexer channels sampled during the current
+    /// period, in the order they were sampled
+    pub fn updated(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.analog_frames.min(self.mux_channels))
+            .map(move |frame| self.mux_channel_for_frame(frame))
+    }
+
+    /// Iterate over all inputs, yielding analog channel, multiplexer
+    /// channel and value
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, f32)> + 'a {
+        let analog_channels = self.analog_channels;
+        self.values
+            .iter()
+            .enumerate()
+            .map(move |(index, &value)| (index % analog_channels, index / analog_channels, value))
+    }
+
+    /// Values of all inputs, holding a block of analog channels for each
+    /// multiplexer channel
+    pub fn as_slice(&self) -> &'a [f32] {
+        self.values
+    }
+}
@@ -0,0 +1,312 @@
+use std::panic::{catch_unwind, UnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+
+use crate::owned_context::OwnedContext;
+use crate::{
+    Bela, BelaApplication, BelaFlags, CleanupTag, Context, Error, RenderTag, SetupContext, SetupTag,
+};
+
+/// Options for rendering a `BelaApplication` offline
+///
+/// Passed to `Bela::render_offline`. By default, silence is fed into the
+/// audio inputs and only the audio outputs are written.
+pub struct OfflineRender {
+    duration: Duration,
+    audio_out: PathBuf,
+    audio_in: Option<PathBuf>,
+    analog_out: Option<PathBuf>,
+}
+
+impl OfflineRender {
+    /// Render `duration` worth of audio to a multichannel WAV file at
+    /// `audio_out`
+    pub fn new(duration: Duration, audio_out: impl Into<PathBuf>) -> Self {
+        Self {
+            duration,
+            audio_out: audio_out.into(),
+            audio_in: None,
+            analog_out: None,
+        }
+    }
+
+    /// Feed the given WAV file into the audio inputs instead of silence
+    ///
+    /// The file's sample rate must match the audio sample rate. Missing
+    /// channels and samples past the end of the file are silent.
+    pub fn audio_in(mut self, path: impl Into<PathBuf>) -> Self {
+        self.audio_in = Some(path.into());
+        self
+    }
+
+    /// Additionally write the analog outputs to a multichannel WAV file
+    /// at the analog sample rate
+    pub fn analog_out(mut self, path: impl Into<PathBuf>) -> Self {
+        self.analog_out = Some(path.into());
+        self
+    }
+}
+
+/// Internal helper to create a 32 bit float WAV writer
+fn create_writer(
+    path: &Path,
+    channels: usize,
+    sample_rate: f32,
+) -> Result<WavWriter<std::io::BufWriter<std::fs::File>>, Error> {
+    let spec = WavSpec {
+        channels: channels as _,
+        sample_rate: sample_rate.round() as _,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    WavWriter::create(path, spec).map_err(|_| Error::Wav)
+}
+
+/// Internal helper to write the first `count` frames of a (possibly
+/// non-interleaved) buffer to a WAV writer, which is always interleaved
+fn write_frames<W>(
+    writer: &mut WavWriter<W>,
+    buffer: &[f32],
+    channels: usize,
+    interleaved: bool,
+    count: usize,
+) -> Result<(), Error>
+where
+    W: std::io::Write + std::io::Seek,
+{
+    if channels == 0 {
+        return Ok(());
+    }
+    let frames = buffer.len() / channels;
+    for frame in 0..frames.min(count) {
+        for channel in 0..channels {
+            let index = if interleaved {
+                frame * channels + channel
+            } else {
+                channel * frames + frame
+            };
+            writer.write_sample(buffer[index]).map_err(|_| Error::Wav)?;
+        }
+    }
+    Ok(())
+}
+
+impl<Application, Constructor> Bela<Constructor>
+where
+    Application: BelaApplication,
+    Constructor: Send + UnwindSafe + FnOnce(&mut SetupContext) -> Option<Application>,
+{
+    /// Consumes the `Bela` object and renders the application offline
+    ///
+    /// Instead of running in real time, `render` is called as fast as
+    /// possible using buffers laid out exactly as configured via the
+    /// builder (period size, channel counts, interleaving and sample rate
+    /// settings), and the outputs are written to WAV files as described
+    /// by `render`. The audio channels and sample rate are those of the
+    /// board selected via `Bela::board`, or of the original Bela.
+    pub fn render_offline(self, render: OfflineRender) -> Result<(), Error> {
+        let Self {
+            settings,
+            constructor,
+            ..
+        } = self.build()?.0;
+
+        let mut context = OwnedContext::new(&settings);
+        context.insert_flags(BelaFlags::OFFLINE);
+        let interleaved = context.is_interleaved();
+        let raw = *context.raw();
+        let audio_frames = raw.audioFrames as usize;
+        let audio_in_channels = raw.audioInChannels as usize;
+        let audio_out_channels = raw.audioOutChannels as usize;
+        let analog_out_channels = raw.analogOutChannels as usize;
+
+        // the input file is read completely, which is fine for offline use
+        let (input, input_channels) = match render.audio_in {
+            Some(path) => {
+                let reader = WavReader::open(path).map_err(|_| Error::Wav)?;
+                let spec = reader.spec();
+                if spec.sample_rate != raw.audioSampleRate.round() as u32 {
+                    return Err(Error::Wav);
+                }
+                let samples: Result<Vec<f32>, _> = match spec.sample_format {
+                    SampleFormat::Float => reader.into_samples::<f32>().collect(),
+                    SampleFormat::Int => {
+                        let scale = 1. / (1u64 << (spec.bits_per_sample - 1)) as f32;
+                        reader
+                            .into_samples::<i32>()
+                            .map(|sample| sample.map(|sample| sample as f32 * scale))
+                            .collect()
+                    }
+                };
+                (samples.map_err(|_| Error::Wav)?, spec.channels as usize)
+            }
+            None => (Vec::new(), 0),
+        };
+        let input_sample = |frame: usize, channel: usize| -> f32 {
+            if channel < input_channels {
+                input
+                    .get(frame * input_channels + channel)
+                    .copied()
+                    .unwrap_or(0.)
+            } else {
+                0.
+            }
+        };
+
+        let mut audio_writer =
+            create_writer(&render.audio_out, audio_out_channels, raw.audioSampleRate)?;
+        let mut analog_writer = match &render.analog_out {
+            Some(path) => Some(create_writer(
+                path,
+                analog_out_channels,
+                raw.analogSampleRate,
+            )?),
+            None => None,
+        };
+
+        let context_ptr = context.as_mut_ptr();
+        let application = catch_unwind(|| {
+            let mut context = unsafe { Context::<SetupTag>::new(context_ptr) };
+            constructor(&mut context)
+        });
+        let mut application = match application {
+            Ok(Some(application)) => application,
+            _ => return Err(Error::Init),
+        };
+
+        // whole periods are rendered, but only `duration` is written
+        let seconds = render.duration.as_secs_f64();
+        let mut audio_remaining = (seconds * raw.audioSampleRate as f64).ceil() as usize;
+        let mut analog_remaining = (seconds * raw.analogSampleRate as f64).ceil() as usize;
+        let periods = (audio_remaining + audio_frames - 1) / audio_frames;
+        for _ in 0..periods {
+            context.begin_period();
+            let elapsed = context.raw().audioFramesElapsed as usize;
+            let audio_in = context.audio_in_mut();
+            for frame in 0..audio_frames {
+                for channel in 0..audio_in_channels {
+                    let index = if interleaved {
+                        frame * audio_in_channels + channel
+                    } else {
+                        channel * audio_frames + frame
+                    };
+                    audio_in[index] = input_sample(elapsed + frame, channel);
+                }
+            }
+
+            let mut render_context = unsafe { Context::<RenderTag>::new(context.as_mut_ptr()) };
+            application.render(&mut render_context);
+            context.end_period();
+
+            write_frames(
+                &mut audio_writer,
+                context.audio_out(),
+                audio_out_channels,
+                interleaved,
+                audio_remaining,
+            )?;
+            audio_remaining = audio_remaining.saturating_sub(audio_frames);
+            if let Some(analog_writer) = &mut analog_writer {
+                write_frames(
+                    analog_writer,
+                    context.analog_out(),
+                    analog_out_channels,
+                    interleaved,
+                    analog_remaining,
+                )?;
+            }
+            analog_remaining = analog_remaining.saturating_sub(raw.analogFrames as usize);
+        }
+
+        let mut cleanup_context = unsafe { Context::<CleanupTag>::new(context.as_mut_ptr()) };
+        application.cleanup(&mut cleanup_context);
+        drop(application);
+
+        audio_writer.finalize().map_err(|_| Error::Wav)?;
+        if let Some(analog_writer) = analog_writer {
+            analog_writer.finalize().map_err(|_| Error::Wav)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BelaHw, RenderContext};
+
+    /// Writes the index of each frame to all audio and analog outputs
+    struct Ramp;
+
+    unsafe impl BelaApplication for Ramp {
+        fn render(&mut self, context: &mut RenderContext) {
+            let elapsed = context.audio_frames_elapsed();
+            let analog_elapsed = elapsed * context.analog_frames() / context.audio_frames();
+            let mut audio_out = context.audio_out_view();
+            for (frame, mut samples) in audio_out.frames_mut().enumerate() {
+                samples.fill((elapsed + frame) as f32);
+            }
+            let mut analog_out = context.analog_out_view();
+            for (frame, mut samples) in analog_out.frames_mut().enumerate() {
+                samples.fill((analog_elapsed + frame) as f32);
+            }
+        }
+    }
+
+    fn read(path: &Path) -> (WavSpec, Vec<f32>) {
+        let reader = WavReader::open(path).unwrap();
+        let spec = reader.spec();
+        let samples = reader.into_samples::<f32>().map(Result::unwrap).collect();
+        (spec, samples)
+    }
+
+    #[test]
+    fn truncates_to_duration() {
+        let dir = std::env::temp_dir();
+        let audio = dir.join(format!("bela-offline-audio-{}.wav", std::process::id()));
+        let analog = dir.join(format!("bela-offline-analog-{}.wav", std::process::id()));
+        // 1 ms is 44.1 audio frames, i.e., two periods of 32 frames
+        let render = OfflineRender::new(Duration::from_millis(1), &audio).analog_out(&analog);
+        Bela::new(|_| Some(Ramp)).render_offline(render).unwrap();
+
+        let (spec, samples) = read(&audio);
+        assert_eq!((spec.channels, spec.sample_rate), (2, 44100));
+        assert_eq!(samples.len(), 45 * 2);
+        for (index, frame) in samples.chunks(2).enumerate() {
+            assert_eq!(frame, [index as f32; 2]);
+        }
+
+        let (spec, samples) = read(&analog);
+        assert_eq!((spec.channels, spec.sample_rate), (8, 22050));
+        assert_eq!(samples.len(), 23 * 8);
+        assert_eq!(samples[22 * 8], 22.);
+
+        std::fs::remove_file(audio).unwrap();
+        std::fs::remove_file(analog).unwrap();
+    }
+
+    #[test]
+    fn uses_board_audio_format() {
+        let audio =
+            std::env::temp_dir().join(format!("bela-offline-ctag-{}.wav", std::process::id()));
+        let render = OfflineRender::new(Duration::from_millis(1), &audio);
+        Bela::new(|_| Some(Ramp))
+            .board(BelaHw::CtagFace)
+            .use_analog(false)
+            .stop_button_pin(None)
+            .amp_mute_pin(None)
+            .render_offline(render)
+            .unwrap();
+
+        // 48 frames at 48 kHz, on all 8 outputs
+        let (spec, samples) = read(&audio);
+        assert_eq!((spec.channels, spec.sample_rate), (8, 48000));
+        assert_eq!(samples.len(), 48 * 8);
+        assert_eq!(samples[47 * 8..], [47.; 8]);
+
+        std::fs::remove_file(audio).unwrap();
+    }
+}
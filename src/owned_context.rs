@@ -0,0 +1,295 @@
+use std::convert::TryInto;
+
+use crate::{BelaFlags, BelaHw, BufferMut};
+
+/// Maximum number of digital channels
+const DIGITAL_CHANNELS: usize = 16;
+
+/// Default settings of the Bela core, for use without the Bela C library
+pub(crate) fn default_settings() -> bela_sys::BelaInitSettings {
+    let mut settings: bela_sys::BelaInitSettings = unsafe { std::mem::zeroed() };
+    settings.periodSize = 16;
+    settings.useAnalog = 1;
+    settings.useDigital = 1;
+    settings.numAnalogInChannels = 8;
+    settings.numAnalogOutChannels = 8;
+    settings.numDigitalChannels = 16;
+    settings.dacLevel = 0.;
+    settings.adcLevel = -6.;
+    settings.pgaGain = [16., 16.];
+    settings.headphoneLevel = -6.;
+    settings.pruNumber = 1;
+    settings.detectUnderruns = 1;
+    settings.enableLED = 1;
+    settings.stopButtonPin = 115;
+    settings.interleave = 1;
+    settings.analogOutputsPersist = 1;
+    settings.audioThreadStackSize = 1 << 20;
+    settings.auxiliaryTaskStackSize = 1 << 20;
+    settings.ampMutePin = 61;
+    settings.board = bela_sys::BelaHw_BelaHw_NoHw;
+    settings
+}
+
+/// Internal `bela_sys::BelaContext` backed by owned buffers
+///
+/// Used by drivers that run `BelaApplication`s without the Bela C
+/// library. The buffer geometry is derived from `BelaInitSettings` the
+/// same way the Bela core derives it from the requested settings.
+pub(crate) struct OwnedContext {
+    context: bela_sys::BelaContext,
+    audio_in: Vec<f32>,
+    audio_out: Vec<f32>,
+    analog_in: Vec<f32>,
+    analog_out: Vec<f32>,
+    digital: Vec<u32>,
+    multiplexer_analog_in: Vec<f32>,
+}
+
+// the raw pointers in `context` only point into the owned buffers
+unsafe impl Send for OwnedContext {}
+
+impl OwnedContext {
+    /// Allocate a context matching the given settings
+    ///
+    /// The audio channels and sample rate are those of the selected
+    /// board, or of the original Bela if no board is selected.
+    pub(crate) fn new(settings: &bela_sys::BelaInitSettings) -> Self {
+        let to_usize = |val: i32| -> usize { val.max(0) as usize };
+
+        let board = BelaHw::from_raw(settings.board)
+            .filter(|&board| board != BelaHw::NoHw)
+            .unwrap_or(BelaHw::Bela)
+            .capabilities();
+        let audio_sample_rate = board.audio_sample_rate;
+
+        let period_size = to_usize(settings.periodSize as _).max(1);
+        let (analog_in_channels, analog_out_channels) = if settings.useAnalog != 0 {
+            (
+                to_usize(settings.numAnalogInChannels as _),
+                to_usize(settings.numAnalogOutChannels as _),
+            )
+        } else {
+            (0, 0)
+        };
+
+        // the analog converter is shared by all channels, so the analog
+        // sample rate is four times the audio rate divided by the channel
+        // count; without analog channels, the 8 channel ratio is used
+        let analog_channels = match analog_in_channels.max(analog_out_channels) {
+            0 => 8,
+            channels => channels,
+        };
+        let analog_frames = period_size;
+        let audio_frames = (period_size * analog_channels / 4).max(1);
+        let (analog_frames, analog_sample_rate) =
+            if settings.uniformSampleRate != 0 && analog_in_channels.max(analog_out_channels) > 0 {
+                (audio_frames, audio_sample_rate)
+            } else {
+                (
+                    analog_frames,
+                    audio_sample_rate * analog_frames as f32 / audio_frames as f32,
+                )
+            };
+
+        let (digital_channels, digital_frames) = if settings.useDigital != 0 {
+            (
+                to_usize(settings.numDigitalChannels as _).min(DIGITAL_CHANNELS),
+                audio_frames,
+            )
+        } else {
+            (0, 0)
+        };
+
+        let multiplexer_channels = if analog_in_channels > 0 {
+            to_usize(settings.numMuxChannels as _)
+        } else {
+            0
+        };
+
+        let mut flags = BelaFlags::empty();
+        if settings.interleave != 0 {
+            flags |= BelaFlags::INTERLEAVED;
+        }
+        if settings.analogOutputsPersist != 0 {
+            flags |= BelaFlags::ANALOG_OUTPUTS_PERSIST;
+        }
+        if settings.detectUnderruns != 0 {
+            flags |= BelaFlags::DETECT_UNDERRUNS;
+        }
+
+        // all digital pins start out as inputs
+        let directions = (1u32 << digital_channels) - 1;
+
+        let mut owned = OwnedContext {
+            context: unsafe { std::mem::zeroed() },
+            audio_in: vec![0.; audio_frames * board.audio_in_channels],
+            audio_out: vec![0.; audio_frames * board.audio_out_channels],
+            analog_in: vec![0.; analog_frames * analog_in_channels],
+            analog_out: vec![0.; analog_frames * analog_out_channels],
+            digital: vec![directions; digital_frames],
+            multiplexer_analog_in: vec![0.; multiplexer_channels * analog_in_channels],
+        };
+
+        let context = &mut owned.context;
+        context.audioIn = owned.audio_in.as_ptr();
+        context.audioOut = owned.audio_out.as_mut_ptr();
+        context.analogIn = owned.analog_in.as_ptr();
+        context.analogOut = owned.analog_out.as_mut_ptr();
+        context.digital = owned.digital.as_mut_ptr();
+        context.audioFrames = audio_frames.try_into().unwrap();
+        context.audioInChannels = board.audio_in_channels.try_into().unwrap();
+        context.audioOutChannels = board.audio_out_channels.try_into().unwrap();
+        context.audioSampleRate = audio_sample_rate;
+        context.analogFrames = analog_frames.try_into().unwrap();
+        context.analogInChannels = analog_in_channels.try_into().unwrap();
+        context.analogOutChannels = analog_out_channels.try_into().unwrap();
+        context.analogSampleRate = analog_sample_rate;
+        context.digitalFrames = digital_frames.try_into().unwrap();
+        context.digitalChannels = digital_channels.try_into().unwrap();
+        context.digitalSampleRate = audio_sample_rate;
+        context.multiplexerChannels = multiplexer_channels.try_into().unwrap();
+        context.multiplexerAnalogIn = if multiplexer_channels > 0 {
+            owned.multiplexer_analog_in.as_ptr()
+        } else {
+            std::ptr::null()
+        };
+        context.flags = flags.bits();
+
+        owned
+    }
+
+    /// Raw pointer to the wrapped C struct, to be passed to
+    /// setup/render/cleanup functions
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut bela_sys::BelaContext {
+        &mut self.context
+    }
+
+    /// Reference to the wrapped C struct
+    pub(crate) fn raw(&self) -> &bela_sys::BelaContext {
+        &self.context
+    }
+
+    /// Access the audio input buffer to fill in input samples
+    pub(crate) fn audio_in_mut(&mut self) -> &mut [f32] {
+        &mut self.audio_in
+    }
+
+    /// Access the analog input buffer to fill in input samples
+    pub(crate) fn analog_in_mut(&mut self) -> &mut [f32] {
+        &mut self.analog_in
+    }
+
+    /// Access the multiplexer analog input buffer to fill in input samples
+    pub(crate) fn multiplexer_analog_in_mut(&mut self) -> &mut [f32] {
+        &mut self.multiplexer_analog_in
+    }
+
+    /// Access the digital buffer to fill in input values
+    pub(crate) fn digital_mut(&mut self) -> &mut [u32] {
+        &mut self.digital
+    }
+
+    /// Access the digital buffer written by `render`
+    pub(crate) fn digital(&self) -> &[u32] {
+        &self.digital
+    }
+
+    /// Access the audio output buffer written by `render`
+    pub(crate) fn audio_out(&self) -> &[f32] {
+        &self.audio_out
+    }
+
+    /// Access the analog output buffer written by `render`
+    pub(crate) fn analog_out(&self) -> &[f32] {
+        &self.analog_out
+    }
+
+    /// Set additional context flags, e.g., `BelaFlags::OFFLINE`
+    pub(crate) fn insert_flags(&mut self, flags: BelaFlags) {
+        self.context.flags |= flags.bits();
+    }
+
+    /// Check if the audio and analog buffers are interleaved
+    pub(crate) fn is_interleaved(&self) -> bool {
+        BelaFlags::from_bits(self.context.flags).is_interleaved()
+    }
+
+    /// Duration of a single period in seconds
+    #[cfg(feature = "simulation")]
+    pub(crate) fn period_duration(&self) -> f64 {
+        self.context.audioFrames as f64 / self.context.audioSampleRate as f64
+    }
+
+    /// Prepare the output buffers for the next call to `render`
+    ///
+    /// Audio and analog outputs are cleared, while digital directions
+    /// and output values persist from the last frame of the previous
+    /// period. The multiplexer starting channel follows the elapsed
+    /// frames.
+    pub(crate) fn begin_period(&mut self) {
+        self.audio_out.iter_mut().for_each(|sample| *sample = 0.);
+        let multiplexer_channels = self.context.multiplexerChannels as u64;
+        if multiplexer_channels > 0 {
+            // the capelet advances by one multiplexer channel per analog frame
+            let analog_frames_elapsed = self.context.audioFramesElapsed
+                * self.context.analogFrames as u64
+                / self.context.audioFrames as u64;
+            self.context.multiplexerStartingChannel =
+                (analog_frames_elapsed % multiplexer_channels) as _;
+        }
+        let flags = BelaFlags::from_bits(self.context.flags);
+        let frames = self.context.analogFrames as usize;
+        let channels = self.context.analogOutChannels as usize;
+        if flags.analog_outputs_persist() && frames > 0 {
+            // like the Bela core, the last value of each channel is held
+            let mut view = BufferMut::new(
+                &mut self.analog_out,
+                frames,
+                channels,
+                flags.is_interleaved(),
+            );
+            for channel in 0..channels {
+                let last = view.read(frames - 1, channel);
+                view.channel_mut(channel).for_each(|sample| *sample = last);
+            }
+        } else {
+            self.analog_out.iter_mut().for_each(|sample| *sample = 0.);
+        }
+        if let Some(&last) = self.digital.last() {
+            // input values are cleared for all pins configured as inputs
+            let directions = last & 0xffff;
+            let persisted = last & !(directions << 16);
+            self.digital.iter_mut().for_each(|word| *word = persisted);
+        }
+    }
+
+    /// Advance the elapsed frame counter after a call to `render`
+    pub(crate) fn end_period(&mut self) {
+        self.context.audioFramesElapsed += self.context.audioFrames as u64;
+    }
+
+    /// Overwrite the elapsed frame counter, e.g., when replaying a log
+    pub(crate) fn set_frames_elapsed(&mut self, frames: u64) {
+        self.context.audioFramesElapsed = frames;
+    }
+
+    /// Overwrite the multiplexer starting channel, e.g., when replaying a
+    /// log
+    pub(crate) fn set_multiplexer_starting_channel(&mut self, channel: usize) {
+        self.context.multiplexerStartingChannel = channel.try_into().unwrap();
+    }
+
+    /// Change the audio sample rate, e.g., to match a log recorded with a
+    /// different codec
+    ///
+    /// The analog and digital sample rates keep their ratio to the audio
+    /// sample rate.
+    pub(crate) fn set_audio_sample_rate(&mut self, sample_rate: f32) {
+        let context = &mut self.context;
+        context.analogSampleRate =
+            sample_rate * context.analogFrames as f32 / context.audioFrames as f32;
+        context.digitalSampleRate = sample_rate;
+        context.audioSampleRate = sample_rate;
+    }
+}
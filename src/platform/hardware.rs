@@ -0,0 +1,89 @@
+use std::ffi::{c_void, CStr};
+use std::os::raw::c_int;
+
+use super::Platform;
+
+/// Platform implementation forwarding to the Bela C API
+pub(crate) struct Hardware;
+
+impl Platform for Hardware {
+    unsafe fn default_settings(settings: *mut bela_sys::BelaInitSettings) {
+        bela_sys::Bela_defaultSettings(settings);
+    }
+
+    unsafe fn init_audio(
+        settings: *mut bela_sys::BelaInitSettings,
+        user_data: *mut c_void,
+    ) -> c_int {
+        bela_sys::Bela_initAudio(settings, user_data)
+    }
+
+    fn start_audio() -> c_int {
+        unsafe { bela_sys::Bela_startAudio() }
+    }
+
+    fn stop_requested() -> bool {
+        unsafe { bela_sys::Bela_stopRequested() != 0 }
+    }
+
+    fn request_stop() {
+        unsafe { bela_sys::Bela_requestStop() }
+    }
+
+    fn stop_audio() {
+        unsafe { bela_sys::Bela_stopAudio() }
+    }
+
+    fn cleanup_audio() {
+        unsafe { bela_sys::Bela_cleanupAudio() }
+    }
+
+    unsafe fn create_auxiliary_task(
+        callback: unsafe extern "C" fn(*mut c_void),
+        priority: c_int,
+        name: &CStr,
+        arg: *mut c_void,
+    ) -> bela_sys::AuxiliaryTask {
+        bela_sys::Bela_createAuxiliaryTask(Some(callback), priority, name.as_ptr(), arg)
+    }
+
+    unsafe fn schedule_auxiliary_task(task: bela_sys::AuxiliaryTask) -> c_int {
+        bela_sys::Bela_scheduleAuxiliaryTask(task)
+    }
+
+    fn detect_hw() -> bela_sys::BelaHw {
+        unsafe { bela_sys::Bela_detectHw() }
+    }
+
+    fn hw_config(hw: bela_sys::BelaHw) -> Option<bela_sys::BelaHwConfig> {
+        unsafe {
+            let config = bela_sys::Bela_HwConfig_new(hw);
+            if config.is_null() {
+                return None;
+            }
+            let copy = *config;
+            bela_sys::Bela_HwConfig_delete(config);
+            Some(copy)
+        }
+    }
+
+    fn set_dac_level(decibels: f32) -> c_int {
+        unsafe { bela_sys::Bela_setDacLevel(decibels) }
+    }
+
+    fn set_adc_level(decibels: f32) -> c_int {
+        unsafe { bela_sys::Bela_setAdcLevel(decibels) }
+    }
+
+    fn set_headphone_level(decibels: f32) -> c_int {
+        unsafe { bela_sys::Bela_setHeadphoneLevel(decibels) }
+    }
+
+    fn set_pga_gain(decibels: f32, channel: c_int) -> c_int {
+        unsafe { bela_sys::Bela_setPgaGain(decibels, channel) }
+    }
+
+    fn mute_speakers(mute: bool) -> c_int {
+        unsafe { bela_sys::Bela_muteSpeakers(mute as _) }
+    }
+}
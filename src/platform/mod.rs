@@ -0,0 +1,95 @@
+use std::ffi::{c_void, CStr};
+use std::os::raw::c_int;
+
+#[cfg(not(feature = "simulation"))]
+mod hardware;
+#[cfg(not(feature = "simulation"))]
+pub(crate) use self::hardware::Hardware as Current;
+
+#[cfg(feature = "simulation")]
+mod simulation;
+#[cfg(feature = "simulation")]
+pub(crate) use self::simulation::Simulation as Current;
+
+/// Internal abstraction over the parts of the Bela C API used by this crate
+///
+/// `Hardware` forwards to `bela_sys`, while `Simulation` (enabled via the
+/// `simulation` feature) is a pure Rust driver for development machines.
+/// The active implementation is available as `platform::Current`.
+pub(crate) trait Platform {
+    /// Fill `settings` with the platform's default settings, mirroring
+    /// `Bela_defaultSettings`
+    ///
+    /// # Safety
+    /// `settings` must point to a valid, writable `BelaInitSettings`
+    unsafe fn default_settings(settings: *mut bela_sys::BelaInitSettings);
+
+    /// Initialize audio and call the `setup` function, mirroring
+    /// `Bela_initAudio`
+    ///
+    /// # Safety
+    /// `settings` must be valid and `user_data` must remain valid until
+    /// `cleanup_audio` has returned
+    unsafe fn init_audio(
+        settings: *mut bela_sys::BelaInitSettings,
+        user_data: *mut c_void,
+    ) -> c_int;
+
+    /// Start the audio thread, mirroring `Bela_startAudio`
+    fn start_audio() -> c_int;
+
+    /// Check if a stop has been requested, mirroring `Bela_stopRequested`
+    fn stop_requested() -> bool;
+
+    /// Request the audio thread to stop, mirroring `Bela_requestStop`
+    ///
+    /// Must be async-signal-safe, as it is called from the signal handler.
+    fn request_stop();
+
+    /// Stop the audio thread, mirroring `Bela_stopAudio`
+    fn stop_audio();
+
+    /// Call the `cleanup` function and release all audio resources,
+    /// mirroring `Bela_cleanupAudio`
+    fn cleanup_audio();
+
+    /// Create an auxiliary task, mirroring `Bela_createAuxiliaryTask`.
+    /// Returns a null pointer on failure.
+    ///
+    /// # Safety
+    /// `arg` must remain valid for as long as the task may run
+    unsafe fn create_auxiliary_task(
+        callback: unsafe extern "C" fn(*mut c_void),
+        priority: c_int,
+        name: &CStr,
+        arg: *mut c_void,
+    ) -> bela_sys::AuxiliaryTask;
+
+    /// Schedule an auxiliary task, mirroring `Bela_scheduleAuxiliaryTask`
+    ///
+    /// # Safety
+    /// `task` must have been created using `create_auxiliary_task`
+    unsafe fn schedule_auxiliary_task(task: bela_sys::AuxiliaryTask) -> c_int;
+
+    /// Detect the connected board, mirroring `Bela_detectHw`
+    fn detect_hw() -> bela_sys::BelaHw;
+
+    /// Query the hardware configuration of `hw`, mirroring
+    /// `Bela_HwConfig_new`. Returns `None` if the board is not supported.
+    fn hw_config(hw: bela_sys::BelaHw) -> Option<bela_sys::BelaHwConfig>;
+
+    /// Set the audio DAC level in dB, mirroring `Bela_setDacLevel`
+    fn set_dac_level(decibels: f32) -> c_int;
+
+    /// Set the audio ADC level in dB, mirroring `Bela_setAdcLevel`
+    fn set_adc_level(decibels: f32) -> c_int;
+
+    /// Set the headphone level in dB, mirroring `Bela_setHeadphoneLevel`
+    fn set_headphone_level(decibels: f32) -> c_int;
+
+    /// Set the PGA gain of `channel` in dB, mirroring `Bela_setPgaGain`
+    fn set_pga_gain(decibels: f32, channel: c_int) -> c_int;
+
+    /// Mute or unmute the speakers, mirroring `Bela_muteSpeakers`
+    fn mute_speakers(mute: bool) -> c_int;
+}
@@ -0,0 +1,213 @@
+use std::ffi::{c_void, CStr};
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::Platform;
+use crate::owned_context::{self, OwnedContext};
+use crate::BelaHw;
+
+/// Pure Rust platform implementation for development machines
+///
+/// Mirrors the behavior of the Bela core: `setup` is called from
+/// `init_audio`, `render` is called periodically from a separate thread
+/// at the rate implied by the settings, with silent inputs, and `cleanup`
+/// is called from `cleanup_audio`.
+pub(crate) struct Simulation;
+
+/// Raw pointer that may be sent to the simulated audio/auxiliary threads
+struct SendPtr(*mut c_void);
+
+unsafe impl Send for SendPtr {}
+
+/// State of a simulated audio session between `init_audio` and
+/// `cleanup_audio`
+struct Session {
+    context: OwnedContext,
+    render: Option<unsafe extern "C" fn(*mut bela_sys::BelaContext, *mut c_void)>,
+    cleanup: Option<unsafe extern "C" fn(*mut bela_sys::BelaContext, *mut c_void)>,
+    user_data: SendPtr,
+    stack_size: usize,
+}
+
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+static AUDIO_THREAD: Mutex<Option<JoinHandle<Session>>> = Mutex::new(None);
+
+impl Platform for Simulation {
+    unsafe fn default_settings(settings: *mut bela_sys::BelaInitSettings) {
+        *settings = owned_context::default_settings();
+    }
+
+    unsafe fn init_audio(
+        settings: *mut bela_sys::BelaInitSettings,
+        user_data: *mut c_void,
+    ) -> c_int {
+        let settings = &*settings;
+        STOP_REQUESTED.store(false, Ordering::SeqCst);
+
+        let mut session = Session {
+            context: OwnedContext::new(settings),
+            render: settings.render,
+            cleanup: settings.cleanup,
+            user_data: SendPtr(user_data),
+            stack_size: settings.audioThreadStackSize as _,
+        };
+
+        if let Some(setup) = settings.setup {
+            if !setup(session.context.as_mut_ptr(), user_data) {
+                return -1;
+            }
+        }
+
+        *SESSION.lock().unwrap() = Some(session);
+        0
+    }
+
+    fn start_audio() -> c_int {
+        let mut session = match SESSION.lock().unwrap().take() {
+            Some(session) => session,
+            None => return -1,
+        };
+
+        let stack_size = session.stack_size.max(1 << 16);
+        let audio_thread = thread::Builder::new()
+            .name("bela-audio".into())
+            .stack_size(stack_size)
+            .spawn(move || {
+                let period = Duration::from_secs_f64(session.context.period_duration());
+                let mut deadline = Instant::now();
+                while !STOP_REQUESTED.load(Ordering::SeqCst) {
+                    session.context.begin_period();
+                    if let Some(render) = session.render {
+                        unsafe { render(session.context.as_mut_ptr(), session.user_data.0) };
+                    }
+                    session.context.end_period();
+
+                    deadline += period;
+                    let now = Instant::now();
+                    if deadline > now {
+                        thread::sleep(deadline - now);
+                    } else {
+                        // fell behind, don't try to catch up
+                        deadline = now;
+                    }
+                }
+                session
+            });
+
+        match audio_thread {
+            Ok(audio_thread) => {
+                *AUDIO_THREAD.lock().unwrap() = Some(audio_thread);
+                0
+            }
+            Err(_) => -1,
+        }
+    }
+
+    fn stop_requested() -> bool {
+        STOP_REQUESTED.load(Ordering::SeqCst)
+    }
+
+    fn request_stop() {
+        STOP_REQUESTED.store(true, Ordering::SeqCst);
+    }
+
+    fn stop_audio() {
+        Self::request_stop();
+        let audio_thread = AUDIO_THREAD.lock().unwrap().take();
+        if let Some(audio_thread) = audio_thread {
+            // if render panicked, the session is lost and cleanup is skipped
+            if let Ok(session) = audio_thread.join() {
+                *SESSION.lock().unwrap() = Some(session);
+            }
+        }
+    }
+
+    fn cleanup_audio() {
+        let session = SESSION.lock().unwrap().take();
+        if let Some(mut session) = session {
+            if let Some(cleanup) = session.cleanup {
+                unsafe { cleanup(session.context.as_mut_ptr(), session.user_data.0) };
+            }
+        }
+    }
+
+    unsafe fn create_auxiliary_task(
+        callback: unsafe extern "C" fn(*mut c_void),
+        _priority: c_int,
+        name: &CStr,
+        arg: *mut c_void,
+    ) -> bela_sys::AuxiliaryTask {
+        // a single pending request is kept, further requests are merged
+        // into it, just like scheduling a Xenomai task that is already
+        // pending
+        let (sender, receiver) = sync_channel::<()>(1);
+        let arg = SendPtr(arg);
+        let task_thread = thread::Builder::new()
+            .name(name.to_string_lossy().into_owned())
+            .spawn(move || {
+                let arg = arg;
+                for () in receiver {
+                    unsafe { callback(arg.0) };
+                }
+            });
+
+        match task_thread {
+            Ok(_) => Box::into_raw(Box::new(sender)) as *mut _,
+            Err(_) => std::ptr::null_mut(),
+        }
+    }
+
+    unsafe fn schedule_auxiliary_task(task: bela_sys::AuxiliaryTask) -> c_int {
+        let sender = &*(task as *const SyncSender<()>);
+        match sender.try_send(()) {
+            Ok(()) | Err(TrySendError::Full(())) => 0,
+            Err(TrySendError::Disconnected(())) => -1,
+        }
+    }
+
+    fn detect_hw() -> bela_sys::BelaHw {
+        // the simulated context mirrors the original Bela
+        BelaHw::Bela as _
+    }
+
+    fn hw_config(hw: bela_sys::BelaHw) -> Option<bela_sys::BelaHwConfig> {
+        let capabilities = BelaHw::from_raw(hw)
+            .filter(|&hw| hw != BelaHw::NoHw)?
+            .capabilities();
+        Some(bela_sys::BelaHwConfig {
+            audioSampleRate: capabilities.audio_sample_rate,
+            audioInChannels: capabilities.audio_in_channels as _,
+            audioOutChannels: capabilities.audio_out_channels as _,
+            analogInChannels: capabilities.analog_in_channels as _,
+            analogOutChannels: capabilities.analog_out_channels as _,
+            digitalChannels: capabilities.digital_channels() as _,
+        })
+    }
+
+    // there is no simulated codec, level changes are accepted and ignored
+
+    fn set_dac_level(_decibels: f32) -> c_int {
+        0
+    }
+
+    fn set_adc_level(_decibels: f32) -> c_int {
+        0
+    }
+
+    fn set_headphone_level(_decibels: f32) -> c_int {
+        0
+    }
+
+    fn set_pga_gain(_decibels: f32, _channel: c_int) -> c_int {
+        0
+    }
+
+    fn mute_speakers(_mute: bool) -> c_int {
+        0
+    }
+}
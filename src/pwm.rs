@@ -0,0 +1,382 @@
+use crate::digital::set_digital_value;
+use crate::{DigitalOut, RenderContext};
+
+/// Software PWM on a digital output
+///
+/// Fills every digital frame of the output channel according to frequency
+/// and duty cycle, carrying the phase across periods. Useful for dimming
+/// LEDs or driving servos:
+///
+/// ```
+/// use bela::{PwmOutput, TestRig};
+///
+/// let mut rig = TestRig::new();
+/// let output = rig.setup(|context| context.digital_output(0)).unwrap();
+/// // 20 ms period with 1.5 ms pulses, servo center position
+/// let mut pwm = PwmOutput::new(output, 50., 0.);
+/// pwm.set_pulse_width(0.0015);
+///
+/// let mut frames = [0u32; 882];
+/// pwm.process_frames(&mut frames, 44100.);
+/// let high = frames.iter().filter(|&&word| word & (1 << 16) != 0).count();
+/// assert_eq!(high, 67);
+/// ```
+#[derive(Debug)]
+pub struct PwmOutput {
+    output: DigitalOut,
+    frequency: f32,
+    duty: f32,
+    /// Position within the current cycle, in `[0, 1)`
+    phase: f64,
+}
+
+impl PwmOutput {
+    /// Create a new `PwmOutput` with the given frequency in Hz and duty
+    /// cycle between 0 and 1
+    pub fn new(output: DigitalOut, frequency: f32, duty: f32) -> Self {
+        let mut pwm = Self {
+            output,
+            frequency: 0.,
+            duty: 0.,
+            phase: 0.,
+        };
+        pwm.set_frequency(frequency);
+        pwm.set_duty(duty);
+        pwm
+    }
+
+    /// The underlying digital output
+    pub fn output(&self) -> &DigitalOut {
+        &self.output
+    }
+
+    /// Frequency in Hz
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    /// Set the frequency in Hz, taking effect without resetting the phase
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency.max(0.);
+    }
+
+    /// Duty cycle between 0 and 1
+    pub fn duty(&self) -> f32 {
+        self.duty
+    }
+
+    /// Set the duty cycle, clamped between 0 and 1
+    pub fn set_duty(&mut self, duty: f32) {
+        self.duty = duty.clamp(0., 1.);
+    }
+
+    /// Set the duty cycle from the width of the pulses in seconds
+    pub fn set_pulse_width(&mut self, seconds: f32) {
+        self.set_duty(seconds * self.frequency);
+    }
+
+    /// Restart the current cycle at the next frame
+    pub fn reset_phase(&mut self) {
+        self.phase = 0.;
+    }
+
+    /// Fill all digital frames of the current period
+    pub fn process(&mut self, context: &mut RenderContext) {
+        let sample_rate = context.digital_sample_rate();
+        self.process_frames(context.digital_mut(), sample_rate);
+    }
+
+    /// Fill raw digital words as found in `RenderContext::digital_mut`,
+    /// see `PwmOutput::process`
+    pub fn process_frames(&mut self, frames: &mut [u32], sample_rate: f32) {
+        let increment = self.frequency as f64 / sample_rate as f64;
+        let duty = self.duty as f64;
+        for word in frames {
+            set_digital_value(word, self.output.channel(), self.phase < duty);
+            self.phase = (self.phase + increment).fract();
+        }
+    }
+}
+
+/// Pulse generator on a digital output
+///
+/// Emits a single pulse of the given width when triggered, or a train of
+/// pulses if an interval is set, low otherwise. Widths and intervals are
+/// in seconds and converted using the digital sample rate.
+///
+/// ```
+/// use bela::{PulseTrain, TestRig};
+///
+/// let mut rig = TestRig::new();
+/// let output = rig.setup(|context| context.digital_output(2)).unwrap();
+/// // 5 ms gate at 1 kHz
+/// let mut gate = PulseTrain::new(output, 0.005);
+///
+/// let mut frames = [0u32; 4];
+/// gate.trigger();
+/// gate.process_frames(&mut frames, 1000.);
+/// assert!(frames.iter().all(|&word| word & (1 << 18) != 0));
+/// gate.process_frames(&mut frames, 1000.);
+/// assert_eq!(frames.map(|word| word & (1 << 18) != 0), [true, false, false, false]);
+/// ```
+#[derive(Debug)]
+pub struct PulseTrain {
+    output: DigitalOut,
+    width: f32,
+    interval: Option<f32>,
+    /// Frames elapsed since triggering, `None` while idle
+    elapsed: Option<u64>,
+}
+
+impl PulseTrain {
+    /// Create a new `PulseTrain` emitting single pulses of `width` seconds
+    pub fn new(output: DigitalOut, width: f32) -> Self {
+        Self {
+            output,
+            width,
+            interval: None,
+            elapsed: None,
+        }
+    }
+
+    /// Repeat the pulses every `seconds` once triggered, until stopped
+    pub fn interval(mut self, seconds: f32) -> Self {
+        self.interval = Some(seconds);
+        self
+    }
+
+    /// The underlying digital output
+    pub fn output(&self) -> &DigitalOut {
+        &self.output
+    }
+
+    /// Set the width of the pulses in seconds
+    pub fn set_width(&mut self, seconds: f32) {
+        self.width = seconds;
+    }
+
+    /// Start a pulse, or the train of pulses, at the next frame
+    ///
+    /// Restarts the pulse if already active.
+    pub fn trigger(&mut self) {
+        self.elapsed = Some(0);
+    }
+
+    /// Stop immediately, setting the output low
+    pub fn stop(&mut self) {
+        self.elapsed = None;
+    }
+
+    /// Check if a pulse or train of pulses is currently active
+    pub fn is_active(&self) -> bool {
+        self.elapsed.is_some()
+    }
+
+    /// Fill all digital frames of the current period
+    pub fn process(&mut self, context: &mut RenderContext) {
+        let sample_rate = context.digital_sample_rate();
+        self.process_frames(context.digital_mut(), sample_rate);
+    }
+
+    /// Fill raw digital words as found in `RenderContext::digital_mut`,
+    /// see `PulseTrain::process`
+    pub fn process_frames(&mut self, frames: &mut [u32], sample_rate: f32) {
+        let width = (self.width * sample_rate).round().max(0.) as u64;
+        let interval = self
+            .interval
+            .map(|interval| ((interval * sample_rate).round() as u64).max(1));
+        for word in frames {
+            let value = match (self.elapsed, interval) {
+                (Some(elapsed), Some(interval)) => elapsed % interval < width,
+                (Some(elapsed), None) if elapsed < width => true,
+                (Some(_), None) => {
+                    self.elapsed = None;
+                    false
+                }
+                (None, _) => false,
+            };
+            set_digital_value(word, self.output.channel(), value);
+            if let Some(elapsed) = &mut self.elapsed {
+                *elapsed += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rig::{digital_levels, digital_outputs};
+
+    /// Levels of channel 0 while processing periods of the given sizes at
+    /// 1 kHz
+    fn levels(periods: &[usize], mut process: impl FnMut(&mut [u32], f32)) -> String {
+        periods
+            .iter()
+            .map(|&frames| {
+                let mut words = vec![0; frames];
+                process(&mut words, 1000.);
+                digital_levels(0, &words)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// PWM on channel 0 with 4 frames per cycle at 1 kHz
+    fn pwm(duty: f32) -> PwmOutput {
+        let [output] = digital_outputs([0]);
+        PwmOutput::new(output, 250., duty)
+    }
+
+    /// Pulses of `width` frames on channel 0 at 1 kHz
+    fn pulses(width: usize) -> PulseTrain {
+        let [output] = digital_outputs([0]);
+        PulseTrain::new(output, width as f32 / 1000.)
+    }
+
+    #[test]
+    fn carries_phase_across_periods() {
+        let mut pwm = pwm(0.5);
+        assert_eq!(
+            levels(&[3, 5, 1], |words, rate| pwm.process_frames(words, rate)),
+            "110 01100 1"
+        );
+
+        // restarting mid-cycle
+        pwm.reset_phase();
+        assert_eq!(
+            levels(&[4], |words, rate| pwm.process_frames(words, rate)),
+            "1100"
+        );
+    }
+
+    #[test]
+    fn changes_take_effect_mid_cycle() {
+        let mut pwm = pwm(0.5);
+        pwm.process_frames(&mut [0; 2], 1000.);
+        pwm.set_duty(0.75);
+        assert_eq!(
+            levels(&[6], |words, rate| pwm.process_frames(words, rate)),
+            "101110"
+        );
+
+        // halving the frequency half way through a cycle keeps the phase
+        pwm.set_duty(0.5);
+        pwm.process_frames(&mut [0; 2], 1000.);
+        pwm.set_frequency(125.);
+        assert_eq!(pwm.frequency(), 125.);
+        assert_eq!(
+            levels(&[6], |words, rate| pwm.process_frames(words, rate)),
+            "000011"
+        );
+    }
+
+    #[test]
+    fn clamps_duty() {
+        let mut pwm = pwm(0.);
+        assert_eq!(
+            levels(&[8], |words, rate| pwm.process_frames(words, rate)),
+            "00000000"
+        );
+
+        pwm.set_duty(1.5);
+        assert_eq!(pwm.duty(), 1.);
+        assert_eq!(
+            levels(&[8], |words, rate| pwm.process_frames(words, rate)),
+            "11111111"
+        );
+
+        pwm.set_duty(-1.);
+        assert_eq!(pwm.duty(), 0.);
+        // 2 ms of a 4 ms cycle
+        pwm.set_pulse_width(0.002);
+        assert_eq!(pwm.duty(), 0.5);
+    }
+
+    #[test]
+    fn zero_frequency_holds_level() {
+        let [output] = digital_outputs([0]);
+        let mut pwm = PwmOutput::new(output, -10., 0.5);
+        assert_eq!(pwm.frequency(), 0.);
+        assert_eq!(
+            levels(&[4, 4], |words, rate| pwm.process_frames(words, rate)),
+            "1111 1111"
+        );
+
+        pwm.set_pulse_width(0.001);
+        assert_eq!(pwm.duty(), 0.);
+        assert_eq!(
+            levels(&[4], |words, rate| pwm.process_frames(words, rate)),
+            "0000"
+        );
+    }
+
+    #[test]
+    fn emits_single_pulse() {
+        let mut pulse = pulses(3);
+        assert_eq!(
+            levels(&[4], |words, rate| pulse.process_frames(words, rate)),
+            "0000"
+        );
+
+        pulse.trigger();
+        assert!(pulse.is_active());
+        assert_eq!(
+            levels(&[2, 3], |words, rate| pulse.process_frames(words, rate)),
+            "11 100"
+        );
+        assert!(!pulse.is_active());
+    }
+
+    #[test]
+    fn retriggers_and_stops() {
+        let mut pulse = pulses(3);
+        pulse.trigger();
+        pulse.process_frames(&mut [0; 2], 1000.);
+        // restarts the full width while active
+        pulse.trigger();
+        assert_eq!(
+            levels(&[5], |words, rate| pulse.process_frames(words, rate)),
+            "11100"
+        );
+
+        pulse.trigger();
+        pulse.process_frames(&mut [0; 1], 1000.);
+        pulse.stop();
+        assert!(!pulse.is_active());
+        assert_eq!(
+            levels(&[3], |words, rate| pulse.process_frames(words, rate)),
+            "000"
+        );
+    }
+
+    #[test]
+    fn repeats_pulses() {
+        let mut pulse = pulses(2).interval(0.004);
+        pulse.trigger();
+        assert_eq!(
+            levels(&[3, 7], |words, rate| pulse.process_frames(words, rate)),
+            "110 0110011"
+        );
+        assert!(pulse.is_active());
+
+        // pulses as wide as the interval or wider merge into a high level
+        for &width in &[4, 6] {
+            let mut pulse = pulses(width).interval(0.004);
+            pulse.trigger();
+            assert_eq!(
+                levels(&[10], |words, rate| pulse.process_frames(words, rate)),
+                "1111111111"
+            );
+        }
+
+        let mut pulse = pulses(2).interval(0.004);
+        pulse.trigger();
+        pulse.process_frames(&mut [0; 5], 1000.);
+        pulse.stop();
+        assert_eq!(
+            levels(&[4], |words, rate| pulse.process_frames(words, rate)),
+            "0000"
+        );
+    }
+}
@@ -0,0 +1,659 @@
+use std::cell::UnsafeCell;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::panic::{catch_unwind, UnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::owned_context::OwnedContext;
+use crate::{
+    Bela, BelaApplication, BelaFlags, CleanupContext, CleanupTag, Context, Error, RenderContext,
+    RenderTag, SetupContext, SetupTag,
+};
+
+/// Magic bytes at the start of every session log
+const MAGIC: &[u8; 8] = b"BELAREC\0";
+/// Version of the session log format
+const VERSION: u32 = 2;
+/// Offset of the dropped period count, which is only known once the
+/// recording ends
+const DROPPED_PERIODS_OFFSET: u64 = 44;
+/// Number of periods that can be buffered before periods are dropped
+const BUFFERED_PERIODS: usize = 256;
+
+/// Buffer geometry stored in the header of a session log
+///
+/// A log can only be replayed using settings resulting in the same
+/// geometry. The sample rate is restored from the log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Header {
+    audio_frames: u32,
+    audio_in_channels: u32,
+    analog_frames: u32,
+    analog_in_channels: u32,
+    multiplexer_channels: u32,
+    digital_frames: u32,
+    flags: u32,
+    audio_sample_rate: f32,
+    /// Number of periods missing from the log
+    dropped_periods: u32,
+}
+
+impl Header {
+    fn from_context<T>(context: &Context<T>) -> Self {
+        let raw = context.raw();
+        Self {
+            audio_frames: raw.audioFrames as _,
+            audio_in_channels: raw.audioInChannels as _,
+            analog_frames: raw.analogFrames as _,
+            analog_in_channels: raw.analogInChannels as _,
+            multiplexer_channels: raw.multiplexerChannels as _,
+            digital_frames: raw.digitalFrames as _,
+            // replays are rendered offline, which must not affect matching
+            flags: raw.flags & !BelaFlags::OFFLINE.bits(),
+            audio_sample_rate: raw.audioSampleRate,
+            dropped_periods: 0,
+        }
+    }
+
+    /// Size of a single period record in bytes
+    fn record_size(&self) -> usize {
+        12 + 4
+            * (self.audio_frames * self.audio_in_channels
+                + (self.analog_frames + self.multiplexer_channels) * self.analog_in_channels
+                + self.digital_frames) as usize
+    }
+
+    fn write(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(MAGIC)?;
+        for val in [
+            VERSION,
+            self.audio_frames,
+            self.audio_in_channels,
+            self.analog_frames,
+            self.analog_in_channels,
+            self.multiplexer_channels,
+            self.digital_frames,
+            self.flags,
+            self.audio_sample_rate.to_bits(),
+            self.dropped_periods,
+        ]
+        .iter()
+        {
+            writer.write_all(&val.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn read(reader: &mut impl Read) -> std::io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        let mut read_u32 = || -> std::io::Result<u32> {
+            let mut bytes = [0u8; 4];
+            reader.read_exact(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        };
+        if &magic != MAGIC || read_u32()? != VERSION {
+            return Err(ErrorKind::InvalidData.into());
+        }
+        Ok(Self {
+            audio_frames: read_u32()?,
+            audio_in_channels: read_u32()?,
+            analog_frames: read_u32()?,
+            analog_in_channels: read_u32()?,
+            multiplexer_channels: read_u32()?,
+            digital_frames: read_u32()?,
+            flags: read_u32()?,
+            audio_sample_rate: f32::from_bits(read_u32()?),
+            dropped_periods: read_u32()?,
+        })
+    }
+}
+
+/// Internal single-producer single-consumer queue of period records
+///
+/// Records are written by the audio thread without allocating or
+/// blocking and read by the writer thread.
+struct RecordQueue {
+    data: UnsafeCell<Box<[u8]>>,
+    record_size: usize,
+    /// Number of records pushed, only modified by the audio thread
+    pushed: AtomicUsize,
+    /// Number of records popped, only modified by the writer thread
+    popped: AtomicUsize,
+    /// Number of records dropped due to a full queue
+    dropped: AtomicUsize,
+    /// Set when the recording ends
+    finished: AtomicBool,
+    /// Set once writing the log failed, and never reset
+    failed: AtomicBool,
+}
+
+// producer and consumer never access the same record concurrently
+unsafe impl Sync for RecordQueue {}
+
+impl RecordQueue {
+    fn new(record_size: usize) -> Self {
+        Self {
+            data: UnsafeCell::new(vec![0; record_size * BUFFERED_PERIODS].into_boxed_slice()),
+            record_size,
+            pushed: AtomicUsize::new(0),
+            popped: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+            finished: AtomicBool::new(false),
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    /// Push a record written by `fill`, or drop it if the queue is full
+    fn push(&self, fill: impl FnOnce(&mut [u8])) {
+        let pushed = self.pushed.load(Ordering::Relaxed);
+        if pushed - self.popped.load(Ordering::Acquire) == BUFFERED_PERIODS {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let offset = (pushed % BUFFERED_PERIODS) * self.record_size;
+        let data = unsafe { &mut *self.data.get() };
+        fill(&mut data[offset..offset + self.record_size]);
+        self.pushed.store(pushed + 1, Ordering::Release);
+    }
+
+    /// Pop all available records and pass them to `drain`
+    fn pop_all(&self, mut drain: impl FnMut(&[u8])) {
+        let pushed = self.pushed.load(Ordering::Acquire);
+        let mut popped = self.popped.load(Ordering::Relaxed);
+        while popped != pushed {
+            let offset = (popped % BUFFERED_PERIODS) * self.record_size;
+            let data = unsafe { &*self.data.get() };
+            drain(&data[offset..offset + self.record_size]);
+            popped += 1;
+            self.popped.store(popped, Ordering::Release);
+        }
+    }
+}
+
+/// Internal handle to the thread writing the session log
+///
+/// Finishes the recording when dropped, if not done before.
+struct RecordWriter {
+    queue: Arc<RecordQueue>,
+    thread: Option<JoinHandle<std::io::Result<()>>>,
+}
+
+impl RecordWriter {
+    /// Signal the writer thread to write all pending records and wait for
+    /// it to finish
+    fn finish(&mut self) -> Result<(), Error> {
+        self.queue.finished.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            if !matches!(thread.join(), Ok(Ok(()))) {
+                self.queue.failed.store(true, Ordering::Relaxed);
+            }
+        }
+        if self.queue.failed.load(Ordering::Relaxed) {
+            Err(Error::Record)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for RecordWriter {
+    fn drop(&mut self) {
+        // failures are reported by `Recorder::finish`
+        let _ = self.finish();
+    }
+}
+
+/// `BelaApplication` wrapper recording all inputs of the wrapped
+/// application
+///
+/// Created via `Bela::record`. Every period, the audio inputs, analog
+/// inputs, multiplexer inputs, digital words and elapsed frame count are
+/// copied to a preallocated queue, which is written to the session log by
+/// a separate thread. If the writer thread cannot keep up, periods are
+/// dropped from the log, and their number is stored in the log once the
+/// recording ends.
+///
+/// The log is complete once the application has been cleaned up, i.e.,
+/// when `Bela::run` returns. Use `Bela::run_and_return` and
+/// `Recorder::finish` to check that it was written successfully.
+pub struct Recorder<Application> {
+    application: Application,
+    writer: RecordWriter,
+}
+
+impl<Application> Recorder<Application> {
+    /// Number of periods that could not be recorded so far
+    pub fn dropped_periods(&self) -> usize {
+        self.writer.queue.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Check if writing the session log failed, e.g., because the disk is
+    /// full
+    ///
+    /// Can be called while recording, and stays set once a write failed.
+    pub fn has_failed(&self) -> bool {
+        self.writer.queue.failed.load(Ordering::Relaxed)
+    }
+
+    /// Stop recording, wait for the session log to be written and return
+    /// the wrapped application
+    ///
+    /// Returns `Error::Record` if the log could not be written completely.
+    pub fn finish(self) -> Result<Application, Error> {
+        let Self {
+            application,
+            mut writer,
+        } = self;
+        writer.finish()?;
+        Ok(application)
+    }
+
+    /// Stop recording and return the wrapped application, see
+    /// `Recorder::finish`
+    pub fn into_inner(self) -> Result<Application, Error> {
+        self.finish()
+    }
+}
+
+unsafe impl<Application> BelaApplication for Recorder<Application>
+where
+    Application: BelaApplication,
+{
+    fn render(&mut self, context: &mut RenderContext) {
+        let frames_elapsed = context.audio_frames_elapsed() as u64;
+        let starting_channel = context.multiplexer_starting_channel() as u32;
+        let audio_in = context.audio_in();
+        let analog_in = context.analog_in();
+        let multiplexer_analog_in = context.multiplexer_analog_in().unwrap_or(&[]);
+        let digital = context.digital();
+        self.writer.queue.push(|record| {
+            let (elapsed, mut record) = record.split_at_mut(8);
+            elapsed.copy_from_slice(&frames_elapsed.to_le_bytes());
+            let words = std::iter::once(starting_channel)
+                .chain(
+                    audio_in
+                        .iter()
+                        .chain(analog_in)
+                        .chain(multiplexer_analog_in)
+                        .map(|sample| sample.to_bits()),
+                )
+                .chain(digital.iter().copied());
+            for word in words {
+                let (bytes, rest) = record.split_at_mut(4);
+                bytes.copy_from_slice(&word.to_le_bytes());
+                record = rest;
+            }
+        });
+        self.application.render(context);
+    }
+
+    fn cleanup(&mut self, context: &mut CleanupContext) {
+        self.application.cleanup(context);
+        // complete the log before `Bela::run` returns, failures are kept
+        // for `Recorder::finish`
+        let _ = self.writer.finish();
+    }
+}
+
+/// Internal function writing records until the recording is finished
+///
+/// Sets the `failed` flag of `queue` on the first error.
+fn write_records(
+    queue: &RecordQueue,
+    mut writer: BufWriter<File>,
+    poll_interval: Duration,
+) -> std::io::Result<()> {
+    let mut result = Ok(());
+    loop {
+        // check before draining, so no records are lost after finishing
+        let finished = queue.finished.load(Ordering::Acquire);
+        queue.pop_all(|record| {
+            if result.is_ok() {
+                result = writer.write_all(record);
+                if result.is_err() {
+                    queue.failed.store(true, Ordering::Relaxed);
+                }
+            }
+        });
+        if finished {
+            break;
+        }
+        thread::sleep(poll_interval);
+    }
+    let result = result.and_then(|()| {
+        let dropped = queue.dropped.load(Ordering::Relaxed) as u32;
+        writer.seek(SeekFrom::Start(DROPPED_PERIODS_OFFSET))?;
+        writer.write_all(&dropped.to_le_bytes())?;
+        writer.flush()
+    });
+    if result.is_err() {
+        queue.failed.store(true, Ordering::Relaxed);
+    }
+    result
+}
+
+impl<Application, Constructor> Bela<Constructor>
+where
+    Application: BelaApplication,
+    Constructor: Send + UnwindSafe + FnOnce(&mut SetupContext) -> Option<Application>,
+{
+    /// Record all inputs of the application to a session log at `path`
+    ///
+    /// The log can be replayed off-device using `Bela::replay` with the
+    /// same settings. If the log cannot be created, setup fails. Errors
+    /// writing the log are reported by `Recorder::finish`.
+    #[allow(clippy::type_complexity)]
+    pub fn record(
+        self,
+        path: impl Into<PathBuf>,
+    ) -> Bela<impl Send + UnwindSafe + FnOnce(&mut SetupContext) -> Option<Recorder<Application>>>
+    {
+        let Self {
+            settings,
+            constructor,
+            issues,
+        } = self;
+        let path = path.into();
+
+        let constructor = move |context: &mut SetupContext| {
+            let header = Header::from_context(context);
+            let mut writer = BufWriter::new(File::create(path).ok()?);
+            header.write(&mut writer).ok()?;
+
+            let queue = Arc::new(RecordQueue::new(header.record_size()));
+            let poll_interval =
+                Duration::from_secs_f32(header.audio_frames as f32 / header.audio_sample_rate);
+            let thread = {
+                let queue = queue.clone();
+                thread::Builder::new()
+                    .name("bela-record".into())
+                    .spawn(move || write_records(&queue, writer, poll_interval))
+                    .ok()?
+            };
+            let writer = RecordWriter {
+                queue,
+                thread: Some(thread),
+            };
+
+            Some(Recorder {
+                application: constructor(context)?,
+                writer,
+            })
+        };
+
+        Bela {
+            settings,
+            constructor,
+            issues,
+        }
+    }
+
+    /// Consumes the `Bela` object and replays a session log recorded
+    /// using `Bela::record`
+    ///
+    /// The application is run as fast as possible, with all inputs, the
+    /// elapsed frame count and the sample rates taken from the log.
+    /// Settings must result in the same buffer layout as during recording.
+    /// Periods dropped during recording are skipped, which shows as a jump
+    /// in the elapsed frame count.
+    pub fn replay(self, path: impl Into<PathBuf>) -> Result<(), Error> {
+        let Self {
+            settings,
+            constructor,
+            ..
+        } = self.build()?.0;
+
+        let file = File::open(path.into()).map_err(|_| Error::Record)?;
+        let mut reader = BufReader::new(file);
+        let header = Header::read(&mut reader).map_err(|_| Error::Record)?;
+
+        let mut context = OwnedContext::new(&settings);
+        context.insert_flags(BelaFlags::OFFLINE);
+        context.set_audio_sample_rate(header.audio_sample_rate);
+        let context_ptr = context.as_mut_ptr();
+        let mut setup_context = unsafe { Context::<SetupTag>::new(context_ptr) };
+        let expected = Header {
+            dropped_periods: header.dropped_periods,
+            ..Header::from_context(&setup_context)
+        };
+        if expected != header {
+            return Err(Error::Record);
+        }
+
+        let application = catch_unwind(move || constructor(&mut setup_context));
+        let mut application = match application {
+            Ok(Some(application)) => application,
+            _ => return Err(Error::Init),
+        };
+
+        let mut record = vec![0u8; header.record_size()];
+        loop {
+            match reader.read_exact(&mut record) {
+                Ok(()) => {}
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(_) => return Err(Error::Record),
+            }
+
+            context.begin_period();
+            let (elapsed, record) = record.split_at(8);
+            context.set_frames_elapsed(u64::from_le_bytes(elapsed.try_into().unwrap()));
+            let mut words = record
+                .chunks_exact(4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()));
+            context.set_multiplexer_starting_channel(words.next().unwrap() as usize);
+            for (sample, word) in context.audio_in_mut().iter_mut().zip(&mut words) {
+                *sample = f32::from_bits(word);
+            }
+            for (sample, word) in context.analog_in_mut().iter_mut().zip(&mut words) {
+                *sample = f32::from_bits(word);
+            }
+            let multiplexer_analog_in = context.multiplexer_analog_in_mut();
+            for (sample, word) in multiplexer_analog_in.iter_mut().zip(&mut words) {
+                *sample = f32::from_bits(word);
+            }
+            // only the input values are replayed, outputs and directions
+            // are up to the application
+            for (current, recorded) in context.digital_mut().iter_mut().zip(&mut words) {
+                let inputs = (recorded & 0xffff) << 16;
+                *current = (*current & !inputs) | (recorded & inputs);
+            }
+
+            let mut render_context = unsafe { Context::<RenderTag>::new(context.as_mut_ptr()) };
+            application.render(&mut render_context);
+            context.end_period();
+        }
+
+        let mut cleanup_context = unsafe { Context::<CleanupTag>::new(context.as_mut_ptr()) };
+        application.cleanup(&mut cleanup_context);
+        drop(application);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Inputs seen by the application in a single period
+    #[derive(Debug, PartialEq)]
+    struct Period {
+        elapsed: usize,
+        sample_rate: f32,
+        starting_channel: usize,
+        audio_in: Vec<f32>,
+        analog_in: Vec<f32>,
+        multiplexer_analog_in: Vec<f32>,
+        digital: Vec<u32>,
+    }
+
+    struct Capture(Arc<Mutex<Vec<Period>>>);
+
+    unsafe impl BelaApplication for Capture {
+        fn render(&mut self, context: &mut RenderContext) {
+            self.0.lock().unwrap().push(Period {
+                elapsed: context.audio_frames_elapsed(),
+                sample_rate: context.audio_sample_rate(),
+                starting_channel: context.multiplexer_starting_channel(),
+                audio_in: context.audio_in().to_vec(),
+                analog_in: context.analog_in().to_vec(),
+                multiplexer_analog_in: context.multiplexer_analog_in().unwrap().to_vec(),
+                digital: context.digital().to_vec(),
+            });
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("bela-{}-{}.log", name, std::process::id()))
+    }
+
+    fn header() -> Header {
+        Header {
+            audio_frames: 16,
+            audio_in_channels: 2,
+            analog_frames: 8,
+            analog_in_channels: 8,
+            multiplexer_channels: 4,
+            digital_frames: 16,
+            flags: BelaFlags::INTERLEAVED.bits(),
+            audio_sample_rate: 48000.,
+            dropped_periods: 3,
+        }
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let mut bytes = Vec::new();
+        header().write(&mut bytes).unwrap();
+        assert_eq!(bytes.len() as u64, DROPPED_PERIODS_OFFSET + 4);
+        assert_eq!(Header::read(&mut &bytes[..]).unwrap(), header());
+
+        bytes[8] = VERSION as u8 + 1;
+        assert!(Header::read(&mut &bytes[..]).is_err());
+        assert!(Header::read(&mut &bytes[..20]).is_err());
+    }
+
+    #[test]
+    fn counts_dropped_periods() {
+        let header = Header {
+            dropped_periods: 0,
+            ..header()
+        };
+        let path = temp_path("dropped");
+        let mut writer = BufWriter::new(File::create(&path).unwrap());
+        header.write(&mut writer).unwrap();
+
+        let queue = RecordQueue::new(header.record_size());
+        for period in 0..BUFFERED_PERIODS + 5 {
+            queue.push(|record| record[0] = period as u8);
+        }
+        queue.finished.store(true, Ordering::Release);
+        write_records(&queue, writer, Duration::from_millis(1)).unwrap();
+
+        let mut reader = BufReader::new(File::open(&path).unwrap());
+        let read = Header::read(&mut reader).unwrap();
+        let mut records = Vec::new();
+        reader.read_to_end(&mut records).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read.dropped_periods, 5);
+        assert_eq!(records.len(), BUFFERED_PERIODS * header.record_size());
+        assert_eq!(records[header.record_size()], 1);
+    }
+
+    #[test]
+    fn reports_write_errors() {
+        let Bela {
+            settings,
+            constructor,
+            ..
+        } = Bela::new(|_: &mut SetupContext| Some(Capture(Default::default())))
+            .num_mux_channels(4)
+            .record("/dev/full");
+        let mut context = OwnedContext::new(&settings);
+        let mut setup_context = unsafe { Context::<SetupTag>::new(context.as_mut_ptr()) };
+        let mut recorder = constructor(&mut setup_context).unwrap();
+        // writes fail as soon as the buffered records are flushed
+        for _ in 0..1000 {
+            if recorder.has_failed() {
+                break;
+            }
+            context.begin_period();
+            let mut render_context = unsafe { Context::<RenderTag>::new(context.as_mut_ptr()) };
+            recorder.render(&mut render_context);
+            context.end_period();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(recorder.has_failed());
+        assert!(matches!(recorder.finish(), Err(Error::Record)));
+    }
+
+    #[test]
+    fn replays_recorded_inputs() {
+        let path = temp_path("replay");
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let Bela {
+            settings,
+            constructor,
+            ..
+        } = {
+            let recorded = recorded.clone();
+            Bela::new(move |_: &mut SetupContext| Some(Capture(recorded)))
+                .num_mux_channels(4)
+                .record(path.clone())
+        };
+
+        // record at 48 kHz, which replays have to restore
+        let mut context = OwnedContext::new(&settings);
+        context.set_audio_sample_rate(48000.);
+        let mut setup_context = unsafe { Context::<SetupTag>::new(context.as_mut_ptr()) };
+        let mut recorder = constructor(&mut setup_context).unwrap();
+        for period in 0..4 {
+            context.begin_period();
+            let value = |index: usize| (period * 1000 + index) as f32;
+            for (index, sample) in context.audio_in_mut().iter_mut().enumerate() {
+                *sample = value(index);
+            }
+            for (index, sample) in context.analog_in_mut().iter_mut().enumerate() {
+                *sample = -value(index);
+            }
+            for (index, sample) in context.multiplexer_analog_in_mut().iter_mut().enumerate() {
+                *sample = value(index) / 2.;
+            }
+            for (index, word) in context.digital_mut().iter_mut().enumerate() {
+                *word |= ((period + index) as u32 & 0xffff) << 16;
+            }
+            let mut render_context = unsafe { Context::<RenderTag>::new(context.as_mut_ptr()) };
+            recorder.render(&mut render_context);
+            context.end_period();
+        }
+        assert_eq!(recorder.dropped_periods(), 0);
+        recorder.finish().unwrap();
+
+        let replayed = Arc::new(Mutex::new(Vec::new()));
+        let result = {
+            let replayed = replayed.clone();
+            Bela::new(move |_: &mut SetupContext| Some(Capture(replayed)))
+                .num_mux_channels(4)
+                .replay(path.clone())
+        };
+        let mismatch = Bela::new(|_: &mut SetupContext| Some(Capture(Default::default())))
+            .replay(path.clone());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_ok());
+        assert!(matches!(mismatch, Err(Error::Record)));
+        let (recorded, replayed) = (recorded.lock().unwrap(), replayed.lock().unwrap());
+        assert_eq!(recorded.len(), 4);
+        assert_eq!(recorded[1].multiplexer_analog_in[5], 2.5 + 500.);
+        assert_eq!(recorded[3].sample_rate, 48000.);
+        assert_eq!(*recorded, *replayed);
+    }
+}
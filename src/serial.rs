@@ -0,0 +1,741 @@
+use crate::digital::set_digital_value;
+use crate::{DigitalIn, DigitalOut, RenderContext};
+
+/// Capacity of the byte queues of `UartTx` and `UartRx`
+const QUEUE_SIZE: usize = 256;
+/// Bits per UART character: start bit, 8 data bits and stop bit
+const UART_BITS: f64 = 10.;
+/// Maximum number of bits in a chain of shift registers
+const MAX_SHIFT_BITS: usize = 32;
+
+/// Internal fixed size FIFO of bytes
+#[derive(Debug)]
+struct ByteQueue {
+    data: [u8; QUEUE_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl ByteQueue {
+    fn new() -> Self {
+        Self {
+            data: [0; QUEUE_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, byte: u8) -> bool {
+        if self.len == QUEUE_SIZE {
+            return false;
+        }
+        self.data[(self.head + self.len) % QUEUE_SIZE] = byte;
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.data[self.head];
+        self.head = (self.head + 1) % QUEUE_SIZE;
+        self.len -= 1;
+        Some(byte)
+    }
+}
+
+/// Internal helper rejecting baud rates that would stall a UART
+fn check_baud(baud: f32) {
+    assert!(baud > 0. && baud.is_finite(), "invalid baud rate {}", baud);
+}
+
+/// Internal helper limiting the baud rate to two frames per bit, so no
+/// bits are skipped
+fn max_baud(baud: f32, sample_rate: f32) -> f64 {
+    (baud as f64).min(sample_rate as f64 / 2.)
+}
+
+/// UART transmitter on a digital output
+///
+/// Sends 8N1 characters, least significant bit first, with the line idle
+/// high. Bit timing is derived from the digital sample rate, so baud
+/// rates should stay well below it, e.g., up to 9600 baud at 44.1 kHz.
+/// Baud rates above half the digital sample rate are limited to it.
+///
+/// ```
+/// use bela::{TestRig, UartTx};
+///
+/// let mut rig = TestRig::new();
+/// let output = rig.setup(|context| context.digital_output(0)).unwrap();
+/// // 4 frames per bit
+/// let mut tx = UartTx::new(output, 11025.);
+/// tx.write(&[0x35]);
+///
+/// let mut frames = [0u32; 48];
+/// tx.process_frames(&mut frames, 44100.);
+/// let bits: Vec<u32> = frames.iter().step_by(4).map(|word| (word >> 16) & 1).collect();
+/// // start bit, 0x35 LSB first, stop bit, idle
+/// assert_eq!(bits, [0, 1, 0, 1, 0, 1, 1, 0, 0, 1, 1, 1]);
+/// assert!(tx.is_idle());
+/// ```
+#[derive(Debug)]
+pub struct UartTx {
+    output: DigitalOut,
+    baud: f32,
+    queue: ByteQueue,
+    /// Character being sent and position within it, in bits
+    current: Option<(u8, f64)>,
+}
+
+impl UartTx {
+    /// Create a new `UartTx` sending at `baud` bits per second
+    ///
+    /// Panics if `baud` is not a positive number.
+    pub fn new(output: DigitalOut, baud: f32) -> Self {
+        check_baud(baud);
+        Self {
+            output,
+            baud,
+            queue: ByteQueue::new(),
+            current: None,
+        }
+    }
+
+    /// The underlying digital output
+    pub fn output(&self) -> &DigitalOut {
+        &self.output
+    }
+
+    /// Queue bytes for sending, returning the number of bytes queued
+    ///
+    /// Up to 256 bytes can be queued, further bytes are rejected.
+    pub fn write(&mut self, bytes: &[u8]) -> usize {
+        bytes
+            .iter()
+            .take_while(|&&byte| self.queue.push(byte))
+            .count()
+    }
+
+    /// Check if all queued bytes have been sent
+    pub fn is_idle(&self) -> bool {
+        self.current.is_none() && self.queue.len == 0
+    }
+
+    /// Fill all digital frames of the current period
+    pub fn process(&mut self, context: &mut RenderContext) {
+        let sample_rate = context.digital_sample_rate();
+        self.process_frames(context.digital_mut(), sample_rate);
+    }
+
+    /// Fill raw digital words as found in `RenderContext::digital_mut`,
+    /// see `UartTx::process`
+    pub fn process_frames(&mut self, frames: &mut [u32], sample_rate: f32) {
+        let increment = max_baud(self.baud, sample_rate) / sample_rate as f64;
+        for word in frames {
+            if self.current.is_none() {
+                self.current = self.queue.pop().map(|byte| (byte, 0.));
+            }
+            let value = match &mut self.current {
+                Some((byte, position)) => {
+                    let value = match *position as usize {
+                        0 => false,
+                        bit @ 1..=8 => (*byte >> (bit - 1)) & 1 != 0,
+                        _ => true,
+                    };
+                    *position += increment;
+                    if *position >= UART_BITS {
+                        // keep the fractional part for the next character
+                        let overshoot = *position - UART_BITS;
+                        self.current = self.queue.pop().map(|byte| (byte, overshoot));
+                    }
+                    value
+                }
+                None => true,
+            };
+            set_digital_value(word, self.output.channel(), value);
+        }
+    }
+}
+
+/// UART receiver on a digital input
+///
+/// Receives 8N1 characters, sampling each bit at its center relative to
+/// the falling edge of the start bit. Characters without a valid stop bit
+/// are dropped and counted as framing errors. Like for `UartTx`, baud
+/// rates above half the digital sample rate are limited to it.
+///
+/// ```
+/// use bela::{TestRig, UartRx, UartTx};
+///
+/// let mut rig = TestRig::new();
+/// let (output, input) = rig.setup(|context| (context.digital_output(0), context.digital_input(1)));
+/// let mut tx = UartTx::new(output.unwrap(), 9600.);
+/// let mut rx = UartRx::new(input.unwrap(), 9600.);
+///
+/// // loop the output back to the input
+/// tx.write(b"Bela");
+/// let mut frames = [0u32; 32];
+/// for _ in 0..20 {
+///     tx.process_frames(&mut frames, 44100.);
+///     for word in frames.iter_mut() {
+///         *word = (*word & !(1 << 17)) | (*word >> 16 & 1) << 17;
+///     }
+///     rx.process_frames(&frames, 44100.);
+/// }
+/// let mut received = [0; 8];
+/// assert_eq!(rx.read(&mut received), 4);
+/// assert_eq!(&received[..4], b"Bela");
+/// ```
+#[derive(Debug)]
+pub struct UartRx {
+    input: DigitalIn,
+    baud: f32,
+    queue: ByteQueue,
+    /// Last value of the line, to detect start bits
+    last: bool,
+    /// Character being received and frames since its start bit
+    current: Option<(u8, f64)>,
+    framing_errors: usize,
+    overruns: usize,
+}
+
+impl UartRx {
+    /// Create a new `UartRx` receiving at `baud` bits per second
+    ///
+    /// Panics if `baud` is not a positive number.
+    pub fn new(input: DigitalIn, baud: f32) -> Self {
+        check_baud(baud);
+        Self {
+            input,
+            baud,
+            queue: ByteQueue::new(),
+            last: true,
+            current: None,
+            framing_errors: 0,
+            overruns: 0,
+        }
+    }
+
+    /// The underlying digital input
+    pub fn input(&self) -> &DigitalIn {
+        &self.input
+    }
+
+    /// Move received bytes into `buf`, returning the number of bytes read
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let mut count = 0;
+        for slot in buf {
+            match self.queue.pop() {
+                Some(byte) => *slot = byte,
+                None => break,
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Take the next received byte
+    pub fn read_byte(&mut self) -> Option<u8> {
+        self.queue.pop()
+    }
+
+    /// Number of characters dropped because of a missing stop bit
+    pub fn framing_errors(&self) -> usize {
+        self.framing_errors
+    }
+
+    /// Number of characters dropped because the queue of 256 received
+    /// bytes was full
+    pub fn overruns(&self) -> usize {
+        self.overruns
+    }
+
+    /// Process the digital frames of the current period
+    pub fn process(&mut self, context: &RenderContext) {
+        self.process_frames(context.digital(), context.digital_sample_rate());
+    }
+
+    /// Process raw digital words as found in `RenderContext::digital`,
+    /// see `UartRx::process`
+    pub fn process_frames(&mut self, frames: &[u32], sample_rate: f32) {
+        let frames_per_bit = sample_rate as f64 / max_baud(self.baud, sample_rate);
+        let bit = self.input.channel() + 16;
+        for word in frames {
+            let value = (word >> bit) & 1 != 0;
+            match &mut self.current {
+                None => {
+                    if self.last && !value {
+                        self.current = Some((0, 0.));
+                    }
+                }
+                Some((byte, elapsed)) => {
+                    *elapsed += 1.;
+                    // center of the bit sampled at this frame, if any
+                    let index = (*elapsed / frames_per_bit - 0.5).floor();
+                    let previous = ((*elapsed - 1.) / frames_per_bit - 0.5).floor();
+                    if index > previous {
+                        match index as usize {
+                            0 if value => self.current = None,
+                            0 => {}
+                            bit @ 1..=8 => *byte |= (value as u8) << (bit - 1),
+                            _ => {
+                                let byte = *byte;
+                                self.current = None;
+                                if !value {
+                                    self.framing_errors += 1;
+                                } else if !self.queue.push(byte) {
+                                    self.overruns += 1;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            self.last = value;
+        }
+    }
+}
+
+/// Driver for chained 74HC595 serial-in, parallel-out shift registers
+///
+/// Shifts out values most significant bit first, so the most significant
+/// byte ends up in the last register of the chain, then pulses the latch
+/// to update the outputs. Each half clock period lasts `clock_frames`
+/// frames.
+///
+/// ```
+/// use bela::{ShiftOut595, TestRig};
+///
+/// let mut rig = TestRig::new();
+/// let pins = rig.setup(|context| {
+///     Ok::<_, bela::Error>((
+///         context.digital_output(0)?,
+///         context.digital_output(1)?,
+///         context.digital_output(2)?,
+///     ))
+/// });
+/// let (data, clock, latch) = pins.unwrap();
+/// let mut shift = ShiftOut595::new(data, clock, latch);
+/// shift.set(0b1100_0101);
+///
+/// let mut frames = [0u32; 32];
+/// shift.process_frames(&mut frames);
+/// // sample the data line at rising clock edges
+/// let bits: Vec<u32> = frames
+///     .windows(2)
+///     .filter(|pair| pair[0] & (1 << 17) == 0 && pair[1] & (1 << 17) != 0)
+///     .map(|pair| (pair[1] >> 16) & 1)
+///     .collect();
+/// assert_eq!(bits, [1, 1, 0, 0, 0, 1, 0, 1]);
+/// assert_eq!(frames[16] & (1 << 18), 1 << 18);
+/// assert!(!shift.is_busy());
+/// ```
+#[derive(Debug)]
+pub struct ShiftOut595 {
+    data: DigitalOut,
+    clock: DigitalOut,
+    latch: DigitalOut,
+    num_bits: usize,
+    clock_frames: usize,
+    /// Value being shifted out and frames since its start
+    current: Option<(u32, usize)>,
+    pending: Option<u32>,
+}
+
+impl ShiftOut595 {
+    /// Create a new `ShiftOut595` for a single register, with a half
+    /// clock period of one frame
+    pub fn new(data: DigitalOut, clock: DigitalOut, latch: DigitalOut) -> Self {
+        Self {
+            data,
+            clock,
+            latch,
+            num_bits: 8,
+            clock_frames: 1,
+            current: None,
+            pending: None,
+        }
+    }
+
+    /// Set the number of chained registers, between 1 and 4
+    pub fn registers(mut self, registers: usize) -> Self {
+        self.num_bits = registers.clamp(1, MAX_SHIFT_BITS / 8) * 8;
+        self
+    }
+
+    /// Set the number of frames per half clock period
+    pub fn clock_frames(mut self, frames: usize) -> Self {
+        self.clock_frames = frames.max(1);
+        self
+    }
+
+    /// Shift out `value` as soon as the current transfer is finished
+    ///
+    /// Values set during a transfer replace each other, only the last one
+    /// is shifted out.
+    pub fn set(&mut self, value: u32) {
+        self.pending = Some(value);
+    }
+
+    /// Check if a transfer is in progress or pending
+    pub fn is_busy(&self) -> bool {
+        self.current.is_some() || self.pending.is_some()
+    }
+
+    /// Fill all digital frames of the current period
+    pub fn process(&mut self, context: &mut RenderContext) {
+        self.process_frames(context.digital_mut());
+    }
+
+    /// Fill raw digital words as found in `RenderContext::digital_mut`,
+    /// see `ShiftOut595::process`
+    pub fn process_frames(&mut self, frames: &mut [u32]) {
+        let half = self.clock_frames;
+        let shift_frames = self.num_bits * 2 * half;
+        for word in frames {
+            if self.current.is_none() {
+                self.current = self.pending.take().map(|value| (value, 0));
+            }
+            let (clock, latch) = match &mut self.current {
+                Some((value, step)) if *step < shift_frames => {
+                    let bit = self.num_bits - 1 - *step / (2 * half);
+                    set_digital_value(word, self.data.channel(), (*value >> bit) & 1 != 0);
+                    let clock = *step % (2 * half) >= half;
+                    *step += 1;
+                    (clock, false)
+                }
+                Some((_, step)) => {
+                    *step += 1;
+                    if *step == shift_frames + half {
+                        self.current = None;
+                    }
+                    (false, true)
+                }
+                None => (false, false),
+            };
+            set_digital_value(word, self.clock.channel(), clock);
+            set_digital_value(word, self.latch.channel(), latch);
+        }
+    }
+}
+
+/// Driver for chained 74HC165 parallel-in, serial-out shift registers
+///
+/// Continuously loads the inputs by pulsing the active low load pin and
+/// clocks them in, most significant bit first, so the inputs of the last
+/// register of the chain end up in the most significant byte. Each half
+/// clock period lasts `clock_frames` frames. As digital outputs only
+/// affect the inputs two periods later by default, the data line is
+/// matched with the clock driven `latency_frames` earlier.
+///
+/// ```
+/// use bela::{ShiftIn165, TestRig};
+///
+/// let mut rig = TestRig::new();
+/// let pins = rig.setup(|context| {
+///     Ok::<_, bela::Error>((
+///         context.digital_input(0)?,
+///         context.digital_output(1)?,
+///         context.digital_output(2)?,
+///     ))
+/// });
+/// let (data, clock, load) = pins.unwrap();
+/// let mut shift = ShiftIn165::new(data, clock, load).latency_frames(0);
+///
+/// // register with inputs 0b1010_0011: load, then shift on rising edges
+/// let (mut register, mut last_clock) = (0u8, false);
+/// let mut frames = [0u32; 64];
+/// for _ in 0..2 {
+///     for word in frames.iter_mut() {
+///         *word = (*word & !(1 << 16)) | ((register >> 7) as u32) << 16;
+///         shift.process_frames(std::slice::from_mut(word));
+///         let (clock, load) = (*word & (1 << 17) != 0, *word & (1 << 18) != 0);
+///         if !load {
+///             register = 0b1010_0011;
+///         } else if clock && !last_clock {
+///             register <<= 1;
+///         }
+///         last_clock = clock;
+///     }
+/// }
+/// assert_eq!(shift.value(), 0b1010_0011);
+/// ```
+#[derive(Debug)]
+pub struct ShiftIn165 {
+    data: DigitalIn,
+    clock: DigitalOut,
+    load: DigitalOut,
+    num_bits: usize,
+    clock_frames: usize,
+    latency_frames: Option<usize>,
+    /// Frames driven since creation
+    elapsed: u64,
+    /// Bits read so far in the current scan
+    shifting: u32,
+    value: u32,
+    scans: usize,
+}
+
+impl ShiftIn165 {
+    /// Create a new `ShiftIn165` for a single register, with a half clock
+    /// period of one frame
+    pub fn new(data: DigitalIn, clock: DigitalOut, load: DigitalOut) -> Self {
+        Self {
+            data,
+            clock,
+            load,
+            num_bits: 8,
+            clock_frames: 1,
+            latency_frames: None,
+            elapsed: 0,
+            shifting: 0,
+            value: 0,
+            scans: 0,
+        }
+    }
+
+    /// Set the number of chained registers, between 1 and 4
+    pub fn registers(mut self, registers: usize) -> Self {
+        self.num_bits = registers.clamp(1, MAX_SHIFT_BITS / 8) * 8;
+        self
+    }
+
+    /// Set the number of frames per half clock period
+    pub fn clock_frames(mut self, frames: usize) -> Self {
+        self.clock_frames = frames.max(1);
+        self
+    }
+
+    /// Set the delay between writing outputs and reading the response on
+    /// the inputs, two periods by default
+    pub fn latency_frames(mut self, frames: usize) -> Self {
+        self.latency_frames = Some(frames);
+        self
+    }
+
+    /// Inputs of the last complete scan
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    /// Number of complete scans, to detect updates of `ShiftIn165::value`
+    pub fn scans(&self) -> usize {
+        self.scans
+    }
+
+    /// Drive and read all digital frames of the current period
+    pub fn process(&mut self, context: &mut RenderContext) {
+        self.process_frames(context.digital_mut());
+    }
+
+    /// Drive and read raw digital words as found in
+    /// `RenderContext::digital_mut`, see `ShiftIn165::process`
+    pub fn process_frames(&mut self, frames: &mut [u32]) {
+        let half = self.clock_frames as u64;
+        // load pulse followed by a clock period per bit
+        let cycle = half + self.num_bits as u64 * 2 * half;
+        let latency = self.latency_frames.unwrap_or(2 * frames.len()) as u64;
+        let bit = self.data.channel() + 16;
+        for word in frames {
+            // read the data line at the end of each low clock phase
+            if let Some(past) = self.elapsed.checked_sub(latency) {
+                let step = past % cycle;
+                if step >= half && (step - half) % (2 * half) == half - 1 {
+                    self.shifting = self.shifting << 1 | (*word >> bit) & 1;
+                    if (step - half) / (2 * half) == self.num_bits as u64 - 1 {
+                        self.value = self.shifting;
+                        self.shifting = 0;
+                        self.scans += 1;
+                    }
+                }
+            }
+
+            let step = self.elapsed % cycle;
+            let load = step >= half;
+            let clock = load && (step - half) % (2 * half) >= half;
+            set_digital_value(word, self.clock.channel(), clock);
+            set_digital_value(word, self.load.channel(), load);
+            self.elapsed += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rig::{digital_inputs, digital_levels, digital_outputs, digital_words};
+
+    /// "Hi" at 9600 baud sampled at 44.1 kHz, i.e., 4.59375 frames per
+    /// bit, after 3 idle frames
+    const HI_9600: &str = "\
+        1110000000000000000000111100000000001111000001\
+        1110000011111000000000111100000111111111000001\
+        111111111";
+
+    /// Ideal 8N1 waveform of `bytes` with the given stop bit level
+    fn waveform(bytes: &[u8], frames_per_bit: f64, stop: bool) -> String {
+        let bits: Vec<bool> = bytes
+            .iter()
+            .flat_map(|&byte| {
+                std::iter::once(false)
+                    .chain((0..8).map(move |bit| (byte >> bit) & 1 != 0))
+                    .chain(std::iter::once(stop))
+            })
+            .collect();
+        let frames = (bits.len() as f64 * frames_per_bit).ceil() as usize;
+        let levels = (0..frames).map(|frame| bits[(frame as f64 / frames_per_bit) as usize]);
+        // idle high before and after, so every start bit has an edge
+        std::iter::once('1')
+            .chain(levels.map(|level| if level { '1' } else { '0' }))
+            .chain("1111".chars())
+            .collect()
+    }
+
+    /// Transmitter on channel 0 and receiver on channel 1 at 9600 baud
+    fn uart() -> (UartTx, UartRx) {
+        let ([output], [input]) = (digital_outputs([0]), digital_inputs([1]));
+        (UartTx::new(output, 9600.), UartRx::new(input, 9600.))
+    }
+
+    fn received(rx: &mut UartRx) -> Vec<u8> {
+        std::iter::from_fn(|| rx.read_byte()).collect()
+    }
+
+    #[test]
+    fn sends_recording() {
+        let (mut tx, _) = uart();
+        tx.write(b"Hi");
+        let mut frames = vec![0u32; HI_9600.len() - 3];
+        // split into periods, which must not affect the bit timing
+        for period in frames.chunks_mut(16) {
+            tx.process_frames(period, 44100.);
+        }
+        assert_eq!(digital_levels(0, &frames), HI_9600[3..]);
+        assert!(tx.is_idle());
+    }
+
+    #[test]
+    fn receives_recording() {
+        let (_, mut rx) = uart();
+        for period in digital_words(&[1], HI_9600).chunks(7) {
+            rx.process_frames(period, 44100.);
+        }
+        assert_eq!(received(&mut rx), b"Hi");
+        assert_eq!((rx.framing_errors(), rx.overruns()), (0, 0));
+    }
+
+    #[test]
+    fn counts_framing_errors() {
+        let (_, mut rx) = uart();
+        let frames_per_bit = 44100. / 9600.;
+        let levels = waveform(b"a", frames_per_bit, false)
+            + &waveform(b"b", frames_per_bit, false)
+            + &waveform(b"c", frames_per_bit, true);
+        rx.process_frames(&digital_words(&[1], &levels), 44100.);
+        assert_eq!(rx.framing_errors(), 2);
+        assert_eq!(received(&mut rx), b"c");
+    }
+
+    #[test]
+    fn counts_overruns() {
+        let (_, mut rx) = uart();
+        let bytes: Vec<u8> = (0..QUEUE_SIZE + 4).map(|byte| byte as u8).collect();
+        // 4 frames per bit
+        rx.process_frames(&digital_words(&[1], &waveform(&bytes, 4., true)), 38400.);
+        assert_eq!(rx.overruns(), 4);
+        let mut buf = [0; QUEUE_SIZE + 4];
+        assert_eq!(rx.read(&mut buf), QUEUE_SIZE);
+        assert_eq!(&buf[..QUEUE_SIZE], &bytes[..QUEUE_SIZE]);
+    }
+
+    #[test]
+    fn limits_baud_rate() {
+        let ([output], [input]) = (digital_outputs([0]), digital_inputs([1]));
+        let mut tx = UartTx::new(output, 1e6);
+        tx.write(&[0x0f]);
+        // two frames per bit at most
+        let mut frames = [0u32; 24];
+        tx.process_frames(&mut frames, 1000.);
+        let levels = digital_levels(0, &frames);
+        assert_eq!(levels, "001111111100000000111111");
+
+        let mut rx = UartRx::new(input, 1e6);
+        rx.process_frames(&digital_words(&[1], &format!("1{}", levels)), 1000.);
+        assert_eq!(received(&mut rx), [0x0f]);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid baud rate 0")]
+    fn rejects_zero_baud() {
+        let [input] = digital_inputs([1]);
+        UartRx::new(input, 0.);
+    }
+
+    #[test]
+    fn shifts_out_chains() {
+        for &(registers, clock_frames) in &[(2, 1), (3, 2), (4, 3)] {
+            let [clock, latch, data] = digital_outputs([1, 2, 3]);
+            let mut shift = ShiftOut595::new(data, clock, latch)
+                .registers(registers)
+                .clock_frames(clock_frames);
+            let mask = (1u64 << (8 * registers)) - 1;
+            let value = (0xdead_beef & mask) as u32;
+            shift.set(value);
+
+            // simulated chain of 74HC595
+            let (mut register, mut outputs) = (0u64, 0u64);
+            let (mut last_clock, mut last_latch) = (false, false);
+            let mut frames = [0u32; 16];
+            while shift.is_busy() {
+                shift.process_frames(&mut frames);
+                for word in &frames {
+                    let (data, clock, latch) = (word >> 19 & 1, word >> 17 & 1, word >> 18 & 1);
+                    if clock != 0 && !last_clock {
+                        register = (register << 1 | data as u64) & mask;
+                    }
+                    if latch != 0 && !last_latch {
+                        outputs = register;
+                    }
+                    last_clock = clock != 0;
+                    last_latch = latch != 0;
+                }
+            }
+            assert_eq!(outputs, value as u64, "{} registers", registers);
+        }
+    }
+
+    #[test]
+    fn shifts_in_chains_with_latency() {
+        for &(registers, clock_frames) in &[(2, 1), (3, 2), (4, 3)] {
+            let ([data], [clock, load]) = (digital_inputs([0]), digital_outputs([1, 2]));
+            let mut shift = ShiftIn165::new(data, clock, load)
+                .registers(registers)
+                .clock_frames(clock_frames);
+            let mask = (1u64 << (8 * registers)) - 1;
+            let inputs = 0x5a3c_81e7 & mask;
+
+            // simulated chain of 74HC165, seeing the outputs two periods late
+            let mut register = 0u64;
+            let mut last_clock = false;
+            let mut outputs = vec![vec![0u32; 32]; 2];
+            while shift.scans() < 2 {
+                let mut frames = outputs[outputs.len() - 2].clone();
+                for word in &mut frames {
+                    let (clock, load) = (*word & (1 << 17) != 0, *word & (1 << 18) != 0);
+                    if !load {
+                        register = inputs;
+                    } else if clock && !last_clock {
+                        register = (register << 1) & mask;
+                    }
+                    last_clock = clock;
+                    let msb = (register >> (8 * registers - 1)) as u32 & 1;
+                    *word = (*word & !(1 << 16)) | msb << 16;
+                }
+                shift.process_frames(&mut frames);
+                outputs.push(frames);
+            }
+            assert_eq!(shift.value() as u64, inputs, "{} registers", registers);
+        }
+    }
+}
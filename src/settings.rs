@@ -0,0 +1,175 @@
+use std::panic::UnwindSafe;
+
+use crate::init_settings::InitSettings;
+use crate::{Bela, BelaApplication, BelaHw, SetupContext};
+
+/// Plain representation of all safe `Bela` settings
+///
+/// Mirrors the builder setters of `Bela` and can be applied using
+/// `Bela::with_settings`. The unsafe PRU settings are not included. With
+/// the `serde` feature enabled, `Settings` can be serialized and
+/// deserialized, e.g., to load per-installation presets from a file.
+/// Missing fields take their default values:
+///
+/// ```toml
+/// period_size = 32
+/// num_analog_in_channels = 4
+/// num_analog_out_channels = 4
+/// headphone_level = -12.0
+/// board = "BelaMini"
+/// stop_button_pin = 115
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct Settings {
+    /// Number of analog frames per period, see `Bela::period_size`
+    pub period_size: usize,
+    /// Whether to use the analog input and output
+    pub use_analog: bool,
+    /// Whether to use the digital input and output
+    pub use_digital: bool,
+    /// Number of analog input channels
+    pub num_analog_in_channels: usize,
+    /// Number of analog output channels
+    pub num_analog_out_channels: usize,
+    /// Number of digital channels
+    pub num_digital_channels: usize,
+    /// Whether to begin with the speakers muted
+    pub begin_muted: bool,
+    /// Level for the audio DAC output
+    pub dac_level: f32,
+    /// Level for the audio ADC input
+    pub adc_level: f32,
+    /// Gains for the PGA, left and right channels
+    pub pga_gain: [f32; 2],
+    /// Level for the headphone output
+    pub headphone_level: f32,
+    /// Number of multiplexer channels
+    pub num_mux_channels: usize,
+    /// Audio expander inputs
+    pub audio_expander_inputs: usize,
+    /// Audio expander outputs
+    pub audio_expander_outputs: usize,
+    /// Whether to detect and log underruns
+    pub detect_underruns: bool,
+    /// Whether to enable verbose logging
+    pub verbose: bool,
+    /// Whether to enable the blinking LED indicating Bela is running
+    pub enable_led: bool,
+    /// Stop button pin (0-127), `None` to disable
+    pub stop_button_pin: Option<i8>,
+    /// Whether to enable high performance mode
+    pub high_performance_mode: bool,
+    /// Whether to interleave audio and analog samples
+    pub interleave: bool,
+    /// Whether analog outputs should persist
+    pub analog_outputs_persist: bool,
+    /// Whether analog inputs should be resampled to audio rate
+    pub uniform_sample_rate: bool,
+    /// Requested audio thread stack size
+    pub audio_thread_stack_size: usize,
+    /// Requested stack size for all auxiliary task threads
+    pub auxiliary_task_stack_size: usize,
+    /// Amplifier mute pin (0-127), `None` to disable
+    pub amp_mute_pin: Option<i8>,
+    /// User selected board, `BelaHw::NoHw` to detect the hardware
+    pub board: BelaHw,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::from_raw(&InitSettings::default())
+    }
+}
+
+/// Internal helper converting a raw pin setting, with negative values
+/// disabling the pin
+fn pin(raw: i64) -> Option<i8> {
+    if raw < 0 {
+        None
+    } else {
+        Some(raw as _)
+    }
+}
+
+impl Settings {
+    /// Internal conversion from raw `bela_sys::BelaInitSettings`
+    fn from_raw(settings: &bela_sys::BelaInitSettings) -> Self {
+        Self {
+            period_size: settings.periodSize as _,
+            use_analog: settings.useAnalog != 0,
+            use_digital: settings.useDigital != 0,
+            num_analog_in_channels: settings.numAnalogInChannels as _,
+            num_analog_out_channels: settings.numAnalogOutChannels as _,
+            num_digital_channels: settings.numDigitalChannels as _,
+            begin_muted: settings.beginMuted != 0,
+            dac_level: settings.dacLevel,
+            adc_level: settings.adcLevel,
+            pga_gain: settings.pgaGain,
+            headphone_level: settings.headphoneLevel,
+            num_mux_channels: settings.numMuxChannels as _,
+            audio_expander_inputs: settings.audioExpanderInputs as _,
+            audio_expander_outputs: settings.audioExpanderOutputs as _,
+            detect_underruns: settings.detectUnderruns != 0,
+            verbose: settings.verbose != 0,
+            enable_led: settings.enableLED != 0,
+            stop_button_pin: pin(settings.stopButtonPin as _),
+            high_performance_mode: settings.highPerformanceMode != 0,
+            interleave: settings.interleave != 0,
+            analog_outputs_persist: settings.analogOutputsPersist != 0,
+            uniform_sample_rate: settings.uniformSampleRate != 0,
+            audio_thread_stack_size: settings.audioThreadStackSize as _,
+            auxiliary_task_stack_size: settings.auxiliaryTaskStackSize as _,
+            amp_mute_pin: pin(settings.ampMutePin as _),
+            board: BelaHw::from_raw(settings.board as _).unwrap_or(BelaHw::NoHw),
+        }
+    }
+}
+
+impl<Application, Constructor> Bela<Constructor>
+where
+    Application: BelaApplication,
+    Constructor: Send + UnwindSafe + FnOnce(&mut SetupContext) -> Option<Application>,
+{
+    /// Apply all `settings` at once
+    ///
+    /// Equivalent to calling every corresponding setter, so values that
+    /// do not fit are reported by `Bela::build`.
+    pub fn with_settings(self, settings: &Settings) -> Self {
+        self.period_size(settings.period_size)
+            .use_analog(settings.use_analog)
+            .use_digital(settings.use_digital)
+            .num_analog_in_channels(settings.num_analog_in_channels)
+            .num_analog_out_channels(settings.num_analog_out_channels)
+            .num_digital_channels(settings.num_digital_channels)
+            .begin_muted(settings.begin_muted)
+            .dac_level(settings.dac_level)
+            .adc_level(settings.adc_level)
+            .pga_gain(settings.pga_gain)
+            .headphone_level(settings.headphone_level)
+            .num_mux_channels(settings.num_mux_channels)
+            .audio_expander_inputs(settings.audio_expander_inputs)
+            .audio_expander_outputs(settings.audio_expander_outputs)
+            .detect_underruns(settings.detect_underruns)
+            .verbose(settings.verbose)
+            .enable_led(settings.enable_led)
+            .stop_button_pin(settings.stop_button_pin)
+            .high_performance_mode(settings.high_performance_mode)
+            .interleave(settings.interleave)
+            .analog_outputs_persist(settings.analog_outputs_persist)
+            .uniform_sample_rate(settings.uniform_sample_rate)
+            .audio_thread_stack_size(settings.audio_thread_stack_size)
+            .auxiliary_task_stack_size(settings.auxiliary_task_stack_size)
+            .amp_mute_pin(settings.amp_mute_pin)
+            .board(settings.board)
+    }
+
+    /// The current settings
+    pub fn settings(&self) -> Settings {
+        Settings::from_raw(&self.settings)
+    }
+}
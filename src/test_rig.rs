@@ -0,0 +1,415 @@
+use crate::owned_context::{self, OwnedContext};
+use crate::{
+    BelaApplication, CleanupContext, CleanupTag, Context, DigitalDirection, RenderContext,
+    RenderTag, SetupContext, SetupTag,
+};
+
+/// Type of scripted analog/audio input functions
+type AnalogScript = Box<dyn FnMut(usize, usize) -> f32>;
+/// Type of scripted multiplexer input functions
+type MuxScript = Box<dyn FnMut(usize, usize, usize) -> f32>;
+/// Type of scripted digital input functions
+type DigitalScript = Box<dyn FnMut(usize, usize) -> bool>;
+
+/// Deterministic harness for testing render code without a Bela
+///
+/// `TestRig` owns all buffers of a Bela context. Inputs are scripted
+/// per frame using closures taking the frame index (counted from the
+/// first rendered period) and the channel index, while outputs of all
+/// rendered periods are captured for later assertions. Captured audio and
+/// analog outputs are always stored interleaved, regardless of the
+/// `interleave` setting.
+///
+/// ```
+/// use bela::{RenderContext, TestRig};
+///
+/// let mut rig = TestRig::new().period_size(8);
+/// rig.script_analog_in(|frame, _channel| frame as f32);
+/// rig.render(2, |context: &mut RenderContext| {
+///     // copy analog inputs to outputs
+///     let analog_in = context.analog_in().to_vec();
+///     context.analog_out().copy_from_slice(&analog_in);
+/// });
+/// // channel 0 of analog frame 9, i.e., frame 1 of the second period
+/// assert_eq!(rig.analog_out()[9 * 8], 9.);
+/// ```
+pub struct TestRig {
+    settings: bela_sys::BelaInitSettings,
+    context: OwnedContext,
+    periods: usize,
+    audio_in: Option<AnalogScript>,
+    analog_in: Option<AnalogScript>,
+    multiplexer_analog_in: Option<MuxScript>,
+    digital_in: Option<DigitalScript>,
+    audio_out: Vec<f32>,
+    analog_out: Vec<f32>,
+    digital: Vec<u32>,
+}
+
+impl Default for TestRig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Internal helper to compute the index of a sample within a buffer
+fn sample_index(
+    interleaved: bool,
+    frame: usize,
+    channel: usize,
+    frames: usize,
+    channels: usize,
+) -> usize {
+    if interleaved {
+        frame * channels + channel
+    } else {
+        channel * frames + frame
+    }
+}
+
+/// Internal helper to append a buffer to an interleaved capture buffer
+fn capture(
+    target: &mut Vec<f32>,
+    buffer: &[f32],
+    frames: usize,
+    channels: usize,
+    interleaved: bool,
+) {
+    for frame in 0..frames {
+        for channel in 0..channels {
+            target.push(buffer[sample_index(interleaved, frame, channel, frames, channels)]);
+        }
+    }
+}
+
+impl TestRig {
+    /// Create a new `TestRig` using the default Bela settings
+    pub fn new() -> Self {
+        let settings = owned_context::default_settings();
+        Self {
+            context: OwnedContext::new(&settings),
+            settings,
+            periods: 0,
+            audio_in: None,
+            analog_in: None,
+            multiplexer_analog_in: None,
+            digital_in: None,
+            audio_out: Vec::new(),
+            analog_out: Vec::new(),
+            digital: Vec::new(),
+        }
+    }
+
+    /// Internal helper to apply a settings change, reallocating the context
+    fn configure(mut self, configure: impl FnOnce(&mut bela_sys::BelaInitSettings)) -> Self {
+        configure(&mut self.settings);
+        self.context = OwnedContext::new(&self.settings);
+        self.periods = 0;
+        self.clear();
+        self
+    }
+
+    /// Set number of analog frames per period (buffer), see `Bela::period_size`
+    pub fn period_size(self, size: usize) -> Self {
+        self.configure(|settings| settings.periodSize = size as _)
+    }
+
+    /// Set whether to use the analog input and output
+    pub fn use_analog(self, use_analog: bool) -> Self {
+        self.configure(|settings| settings.useAnalog = use_analog as _)
+    }
+
+    /// Set whether to use the digital input and output
+    pub fn use_digital(self, use_digital: bool) -> Self {
+        self.configure(|settings| settings.useDigital = use_digital as _)
+    }
+
+    /// Set number of analog input channels
+    pub fn num_analog_in_channels(self, num: usize) -> Self {
+        self.configure(|settings| settings.numAnalogInChannels = num as _)
+    }
+
+    /// Set number of analog output channels
+    pub fn num_analog_out_channels(self, num: usize) -> Self {
+        self.configure(|settings| settings.numAnalogOutChannels = num as _)
+    }
+
+    /// Set number of digital channels
+    pub fn num_digital_channels(self, num: usize) -> Self {
+        self.configure(|settings| settings.numDigitalChannels = num as _)
+    }
+
+    /// Set the number of multiplexer channels
+    pub fn num_mux_channels(self, num: usize) -> Self {
+        self.configure(|settings| settings.numMuxChannels = num as _)
+    }
+
+    /// Enable or disable interleaving of audio and analog samples
+    pub fn interleave(self, val: bool) -> Self {
+        self.configure(|settings| settings.interleave = val as _)
+    }
+
+    /// Set if analog outputs should persist
+    pub fn analog_outputs_persist(self, val: bool) -> Self {
+        self.configure(|settings| settings.analogOutputsPersist = val as _)
+    }
+
+    /// Set if analog inputs should be resampled to audio rate
+    pub fn uniform_sample_rate(self, val: bool) -> Self {
+        self.configure(|settings| settings.uniformSampleRate = val as _)
+    }
+
+    /// Script the audio inputs as a function of audio frame and channel
+    pub fn script_audio_in(&mut self, script: impl FnMut(usize, usize) -> f32 + 'static) {
+        self.audio_in = Some(Box::new(script));
+    }
+
+    /// Script the analog inputs as a function of analog frame and channel
+    pub fn script_analog_in(&mut self, script: impl FnMut(usize, usize) -> f32 + 'static) {
+        self.analog_in = Some(Box::new(script));
+    }
+
+    /// Script the multiplexer analog inputs as a function of analog
+    /// frame, analog channel and multiplexer channel. Only the
+    /// multiplexer channel sampled at each analog frame is updated, like
+    /// on the capelet.
+    pub fn script_multiplexer_analog_in(
+        &mut self,
+        script: impl FnMut(usize, usize, usize) -> f32 + 'static,
+    ) {
+        self.multiplexer_analog_in = Some(Box::new(script));
+    }
+
+    /// Script the digital inputs as a function of digital frame and
+    /// channel. Only pins configured as inputs are affected.
+    pub fn script_digital_in(&mut self, script: impl FnMut(usize, usize) -> bool + 'static) {
+        self.digital_in = Some(Box::new(script));
+    }
+
+    /// Call `setup` with the rig's context, e.g., to construct a
+    /// `BelaApplication`
+    pub fn setup<R>(&mut self, setup: impl FnOnce(&mut SetupContext) -> R) -> R {
+        let mut context = unsafe { Context::<SetupTag>::new(self.context.as_mut_ptr()) };
+        setup(&mut context)
+    }
+
+    /// Call `cleanup` with the rig's context, e.g., to test
+    /// `BelaApplication::cleanup`
+    pub fn cleanup<R>(&mut self, cleanup: impl FnOnce(&mut CleanupContext) -> R) -> R {
+        let mut context = unsafe { Context::<CleanupTag>::new(self.context.as_mut_ptr()) };
+        cleanup(&mut context)
+    }
+
+    /// Render `periods` periods of the given application
+    pub fn run<Application: BelaApplication>(
+        &mut self,
+        application: &mut Application,
+        periods: usize,
+    ) {
+        self.render(periods, |context| application.render(context));
+    }
+
+    /// Render `periods` periods using the given `render` function
+    pub fn render(&mut self, periods: usize, mut render: impl FnMut(&mut RenderContext)) {
+        for _ in 0..periods {
+            self.context.begin_period();
+            self.fill_inputs();
+
+            let mut context = unsafe { Context::<RenderTag>::new(self.context.as_mut_ptr()) };
+            render(&mut context);
+
+            self.context.end_period();
+            self.capture_outputs();
+            self.periods += 1;
+        }
+    }
+
+    /// Internal helper to fill all inputs from the scripts
+    fn fill_inputs(&mut self) {
+        let raw = *self.context.raw();
+        let interleaved = self.context.is_interleaved();
+
+        let audio_frames = raw.audioFrames as usize;
+        let audio_channels = raw.audioInChannels as usize;
+        if let Some(script) = &mut self.audio_in {
+            let audio_in = self.context.audio_in_mut();
+            let first_frame = self.periods * audio_frames;
+            for frame in 0..audio_frames {
+                for channel in 0..audio_channels {
+                    let index =
+                        sample_index(interleaved, frame, channel, audio_frames, audio_channels);
+                    audio_in[index] = script(first_frame + frame, channel);
+                }
+            }
+        }
+
+        let analog_frames = raw.analogFrames as usize;
+        let analog_channels = raw.analogInChannels as usize;
+        if let Some(script) = &mut self.analog_in {
+            let analog_in = self.context.analog_in_mut();
+            let first_frame = self.periods * analog_frames;
+            for frame in 0..analog_frames {
+                for channel in 0..analog_channels {
+                    let index =
+                        sample_index(interleaved, frame, channel, analog_frames, analog_channels);
+                    analog_in[index] = script(first_frame + frame, channel);
+                }
+            }
+        }
+
+        let multiplexer_channels = raw.multiplexerChannels as usize;
+        let starting_channel = raw.multiplexerStartingChannel as usize;
+        if let Some(script) = self
+            .multiplexer_analog_in
+            .as_mut()
+            .filter(|_| multiplexer_channels > 0)
+        {
+            let multiplexer_analog_in = self.context.multiplexer_analog_in_mut();
+            let first_frame = self.periods * analog_frames;
+            for frame in 0..analog_frames {
+                let mux_channel = (starting_channel + frame) % multiplexer_channels;
+                for channel in 0..analog_channels {
+                    multiplexer_analog_in[mux_channel * analog_channels + channel] =
+                        script(first_frame + frame, channel, mux_channel);
+                }
+            }
+        }
+
+        let digital_frames = raw.digitalFrames as usize;
+        let digital_channels = raw.digitalChannels as usize;
+        if let Some(script) = &mut self.digital_in {
+            let digital = self.context.digital_mut();
+            let first_frame = self.periods * digital_frames;
+            for (frame, word) in digital.iter_mut().enumerate() {
+                for channel in 0..digital_channels {
+                    if *word & (1 << channel) == 0 {
+                        continue;
+                    }
+                    if script(first_frame + frame, channel) {
+                        *word |= 1 << (channel + 16);
+                    } else {
+                        *word &= !(1 << (channel + 16));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Internal helper to append the outputs of the last period to the
+    /// captured outputs
+    fn capture_outputs(&mut self) {
+        let raw = *self.context.raw();
+        let interleaved = self.context.is_interleaved();
+        capture(
+            &mut self.audio_out,
+            self.context.audio_out(),
+            raw.audioFrames as usize,
+            raw.audioOutChannels as usize,
+            interleaved,
+        );
+        capture(
+            &mut self.analog_out,
+            self.context.analog_out(),
+            raw.analogFrames as usize,
+            raw.analogOutChannels as usize,
+            interleaved,
+        );
+        self.digital.extend_from_slice(self.context.digital());
+    }
+
+    /// Number of periods rendered so far
+    pub fn periods(&self) -> usize {
+        self.periods
+    }
+
+    /// Captured audio outputs of all rendered periods, interleaved
+    pub fn audio_out(&self) -> &[f32] {
+        &self.audio_out
+    }
+
+    /// Captured analog outputs of all rendered periods, interleaved
+    pub fn analog_out(&self) -> &[f32] {
+        &self.analog_out
+    }
+
+    /// Captured digital words (inputs, outputs and directions) of all
+    /// rendered periods
+    pub fn digital(&self) -> &[u32] {
+        &self.digital
+    }
+
+    /// Captured value of a digital output channel at the given frame,
+    /// counted from the first captured period
+    pub fn digital_out(&self, frame: usize, channel: usize) -> bool {
+        (self.digital[frame] >> (channel + 16)) & 1 != 0
+    }
+
+    /// Captured direction of a digital channel at the given frame,
+    /// counted from the first captured period
+    pub fn pin_mode(&self, frame: usize, channel: usize) -> DigitalDirection {
+        if (self.digital[frame] >> channel) & 1 != 0 {
+            DigitalDirection::Input
+        } else {
+            DigitalDirection::Output
+        }
+    }
+
+    /// Discard all captured outputs
+    pub fn clear(&mut self) {
+        self.audio_out.clear();
+        self.analog_out.clear();
+        self.digital.clear();
+    }
+}
+
+/// Internal test helper configuring digital inputs on a new `TestRig`
+#[cfg(test)]
+pub(crate) fn digital_inputs<const N: usize>(channels: [usize; N]) -> [crate::DigitalIn; N] {
+    TestRig::new().setup(|context| channels.map(|channel| context.digital_input(channel).unwrap()))
+}
+
+/// Internal test helper configuring digital outputs on a new `TestRig`
+#[cfg(test)]
+pub(crate) fn digital_outputs<const N: usize>(channels: [usize; N]) -> [crate::DigitalOut; N] {
+    TestRig::new().setup(|context| channels.map(|channel| context.digital_output(channel).unwrap()))
+}
+
+/// Internal test helper creating digital words from a string of levels,
+/// e.g., `"00 10 11"` for three frames of `channels` 0 and 1
+///
+/// Whitespace is ignored, every other character is the level of the next
+/// channel, with `'1'` being high.
+#[cfg(test)]
+pub(crate) fn digital_words(channels: &[usize], levels: &str) -> Vec<u32> {
+    let levels: Vec<char> = levels
+        .chars()
+        .filter(|level| !level.is_whitespace())
+        .collect();
+    assert_eq!(levels.len() % channels.len(), 0, "incomplete frame");
+    levels
+        .chunks(channels.len())
+        .map(|frame| {
+            channels
+                .iter()
+                .zip(frame)
+                .map(|(&channel, &level)| ((level == '1') as u32) << (channel + 16))
+                .sum()
+        })
+        .collect()
+}
+
+/// Internal test helper formatting the values of a digital channel as a
+/// string of levels, the inverse of `digital_words`
+#[cfg(test)]
+pub(crate) fn digital_levels(channel: usize, words: &[u32]) -> String {
+    words
+        .iter()
+        .map(|word| {
+            if word & (1 << (channel + 16)) != 0 {
+                '1'
+            } else {
+                '0'
+            }
+        })
+        .collect()
+}